@@ -1,6 +1,10 @@
 use std::f32::consts::PI;
 
-use crate::{PlanetaryObjectNames, ObjectName};
+use crate::{
+    BodyCategory, BodyId, BodyRegistry, BodySelectorButton, CameraFollow, CameraSetting, CameraSettingButton,
+    DisplayScale, GuidedTour, InsetFrame, MapView, ParentRelativeOrbit
+};
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
 use bevy::prelude::*;
 use crate::SolarSystemObjectData;
 
@@ -8,21 +12,42 @@ pub const CAMERA_MOVE_SPEED: f32 = 200.0;
 pub const CAMERA_PAN_SPEED: f32 = 0.5;
 pub const CAMERA_CHANGE_TIME: f32 = 100.0;
 pub const ORBITAL_SPEED_CONSTANT: f32 = 100.0;
-pub const CHANGE_CAMERA_PLANET_TIME: f32 = 3.0;
+pub const CHANGE_CAMERA_PLANET_TIME: f32 = 5.0; // including the flight there
+pub const CAMERA_FLIGHT_TIME: f32 = 2.0;
+pub const CAMERA_FLIGHT_ZOOM_OUT: f32 = 0.5; // of the distance flown, at the middle of a flight
+pub const MOUSE_ORBIT_SPEED: f32 = 0.005; // radians per pixel dragged
+pub const MOUSE_PAN_SPEED: f32 = 0.002; // of the focus distance per pixel dragged
+pub const SCROLL_ZOOM_SPEED: f32 = 0.15; // log of the zoom factor per wheel line
+pub const SCROLL_PIXELS_PER_LINE: f32 = 50.0; // for touchpads that scroll by pixel
+pub const MIN_FOCUS_DISTANCE: f32 = 1.0e-4; // well inside the Moon's true radius
+pub const MAX_FOCUS_DISTANCE: f32 = 1.0e6;
+pub const CAMERA_NEAR_PLANE: f32 = 1.0e-5; // close enough to see bodies drawn at true scale
+pub const AUTOMATIC_CAMERA_HEIGHT: f32 = 0.4; // of the orbit distance, above the planet
+// categories of body the automatic camera cycles through
+pub const AUTOMATIC_CAMERA_CATEGORIES: [BodyCategory; 2] = [BodyCategory::Planet, BodyCategory::DwarfPlanet];
 
 // SETUP A RESOURCE TO LOOP THROUGH PLANETS
 // FOR THE AUTOMATIC CAMERA TO FOCUS ON
-#[derive(Reflect, Resource, PartialEq)]
+#[derive(Reflect, Resource, Default, PartialEq)]
 pub struct CurrentPlanetForCamera {
-    pub planet: PlanetaryObjectNames
+    pub body: Option<BodyId>
 }
 
-impl Default for CurrentPlanetForCamera {
-    fn default() -> CurrentPlanetForCamera {
-        CurrentPlanetForCamera {planet: PlanetaryObjectNames::Mercury}
-    }
+// labels so other plugins can order their systems around the camera's
+#[derive(SystemLabel)]
+pub enum CameraSystem {
+    Automatic
+}
+
+// SENT WHEN THE USER PICKS A BODY, FROM THE PANEL OR BY CLICKING ON IT
+pub struct BodySelected {
+    pub body: BodyId
 }
 
+// THE CAMERA THE USER FLIES, AS OPPOSED TO THE MAP AND PICTURE IN PICTURE ONES
+#[derive(Component)]
+pub struct MainCamera;
+
 // SETUP AUTOMATIC CAMERA
 #[derive(Reflect, Component, Default, Debug)]
 #[reflect(Component)]
@@ -30,10 +55,37 @@ pub struct AutomaticCameraParameters {
     pub orbit_angle: f32,
     pub orbit_speed: f32,
     pub orbit_distance: f32,
-    pub viewing_height: f32,
-    pub automatic_on: bool
+    pub viewing_height: f32
 }
 
+// A FLIGHT OF THE AUTOMATIC CAMERA TOWARDS ITS TARGET
+// starting from wherever the camera was, zooming out, across and back in
+// to the target's orbit framing; target is None until the camera has a planet
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct CameraFlight {
+    pub target: Option<Entity>,
+    pub duration: f32,
+    pub elapsed: f32,
+    pub start_translation: Vec3,
+    pub start_rotation: Quat,
+    pub start_focus: Vec3
+}
+
+// MOUSE CONTROLS FOR THE MANUAL CAMERA
+// the camera orbits and zooms around a focus point focus_distance straight ahead,
+// so moving it with the keyboard carries the focus point along
+#[derive(Reflect, Component, Debug)]
+#[reflect(Component)]
+pub struct ManualCameraController {
+    pub focus_distance: f32
+}
+
+impl Default for ManualCameraController {
+    fn default() -> ManualCameraController {
+        ManualCameraController { focus_distance: 400.0 }
+    }
+}
 
 // RESOURCE TIMER TO CHANGE BETWEEN AUTOMATIC AND MANUAL CAMERA
 #[derive(Reflect)]
@@ -70,69 +122,140 @@ pub struct UserCameraPlugin;
 impl Plugin for UserCameraPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<AutomaticCameraParameters>()
+        .register_type::<ManualCameraController>()
+        .register_type::<CameraFlight>()
         .register_type::<CameraViewTimer>()
         .init_resource::<CameraViewTimer>()
         .register_type::<PlanetCameraChangeTimer>()
         .init_resource::<PlanetCameraChangeTimer>()
         .register_type::<CurrentPlanetForCamera>()
         .init_resource::<CurrentPlanetForCamera>()
+        .register_type::<CameraSetting>()
+        .init_resource::<CameraSetting>()
+        .add_event::<BodySelected>()
         .add_startup_system(add_camera)
         .add_system(camera_controls)
         .add_system(camera_pan)
-        .add_system(automatic_camera)
+        .add_system(mouse_camera_controls)
+        .add_system(automatic_camera.label(CameraSystem::Automatic))
         .add_system(change_automatic_camera_planet)
-        .add_system(change_camera_view_type);
+        .add_system(change_camera_view_type)
+        .add_system(camera_setting_buttons)
+        .add_system(body_selector_buttons)
+        .add_system(focus_selected_body.after(body_selector_buttons));
     }
 }
 
-// add a timer to change planet view in automatic mode
+// add a timer to change planet view in automatic mode,
+// cycling through the registered bodies in catalog order
 fn change_automatic_camera_planet(
     mut planet_timer: ResMut<PlanetCameraChangeTimer>,
     mut current_planet: ResMut<CurrentPlanetForCamera>,
+    orbit_query: Query<&ParentRelativeOrbit>,
+    registry: Res<BodyRegistry>,
     time: Res<Time>
 ){
     planet_timer.timer.tick(time.delta());
-    if planet_timer.timer.just_finished() {
-        current_planet.planet = match current_planet.planet {
-            PlanetaryObjectNames::Mercury => PlanetaryObjectNames::Venus,
-            PlanetaryObjectNames::Venus => PlanetaryObjectNames::Earth,
-            PlanetaryObjectNames::Earth => PlanetaryObjectNames::Mars,
-            PlanetaryObjectNames::Mars => PlanetaryObjectNames::Jupiter,
-            PlanetaryObjectNames::Jupiter => PlanetaryObjectNames::Saturn,
-            PlanetaryObjectNames::Saturn => PlanetaryObjectNames::Uranus,
-            PlanetaryObjectNames::Uranus => PlanetaryObjectNames::Neptune,
-            PlanetaryObjectNames::Neptune => PlanetaryObjectNames::Mercury,
-        };
-        println!("Current Planet: {:?}", current_planet.planet);
+    let current_missing: bool = current_planet.body
+        .is_none_or(|id| registry.get(id).is_none());
+    if planet_timer.timer.just_finished() || current_missing {
+        let next_body: Option<BodyId> =
+            registry.next_in_categories(current_planet.body, &AUTOMATIC_CAMERA_CATEGORIES);
+        if next_body == current_planet.body {
+            return;
+        }
+        current_planet.body = next_body;
+        if let Some(body) = next_body.and_then(|id| registry.get(id)) {
+            match (registry.parent_of(body.id), orbit_query.get(body.entity)) {
+                (Some(parent), Ok(orbit)) => println!(
+                    "Current Planet: {} ({:.0} km from {} at {:.2} km/s, e = {:.4})",
+                    body.name, orbit.distance_m / 1000.0, parent.name, orbit.speed_m_s / 1000.0,
+                    orbit.elements.eccentricity
+                ),
+                _ => println!("Current Planet: {}", body.name)
+            }
+        }
     }
 }
 
-// add a timer to change camera view from auto to manual after a set period of time
+// add a timer to change camera view from auto to manual after a set period of time,
+// until the camera is set from the UI
 fn change_camera_view_type(
-    mut commands: Commands,
     mut camera_timer: ResMut<CameraViewTimer>,
-    mut camera_query: Query<&mut AutomaticCameraParameters, With<Camera3d>>,
+    mut camera_setting: ResMut<CameraSetting>,
     time: Res<Time>
 ) {    
     camera_timer.timer.tick(time.delta());
     if camera_timer.timer.just_finished() {
         println!("Ding Dong");
-        if let Ok(mut automatic_camera) = 
-        camera_query.get_single_mut() {
-            match automatic_camera.automatic_on {
-                false => automatic_camera.automatic_on = true,
-                true => automatic_camera.automatic_on = false
+        *camera_setting = match *camera_setting {
+            CameraSetting::Manual => CameraSetting::Automatic,
+            CameraSetting::Automatic => CameraSetting::Manual
+        };
+    }
+}
+
+// choosing Automatic also goes back to cycling through the planets
+fn camera_setting_buttons(
+    mut camera_setting: ResMut<CameraSetting>,
+    mut camera_timer: ResMut<CameraViewTimer>,
+    mut planet_timer: ResMut<PlanetCameraChangeTimer>,
+    button_query: Query<(&Interaction, &CameraSettingButton), Changed<Interaction>>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked {
+            *camera_setting = button.button_type;
+            camera_timer.timer.pause();
+            if button.button_type == CameraSetting::Automatic {
+                planet_timer.timer.reset();
+                planet_timer.timer.unpause();
             }
         }
     }
 }
 
+fn body_selector_buttons(
+    mut selected_events: EventWriter<BodySelected>,
+    button_query: Query<(&Interaction, &BodySelectorButton), Changed<Interaction>>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked {
+            selected_events.send(BodySelected { body: button.body });
+        }
+    }
+}
+
+// a selected body holds the automatic camera on it instead of cycling,
+// which also brings up its information card. While the manual camera is
+// following a body, selecting only changes the focus, ready to look at it
+fn focus_selected_body(
+    mut selected_events: EventReader<BodySelected>,
+    mut current_planet: ResMut<CurrentPlanetForCamera>,
+    mut camera_setting: ResMut<CameraSetting>,
+    mut camera_timer: ResMut<CameraViewTimer>,
+    mut planet_timer: ResMut<PlanetCameraChangeTimer>,
+    follow_query: Query<&CameraFollow>
+) {
+    let Some(selected) = selected_events.iter().last() else { return };
+    current_planet.body = Some(selected.body);
+    if follow_query.iter().any(|follow| follow.is_following()) && *camera_setting == CameraSetting::Manual {
+        return;
+    }
+    *camera_setting = CameraSetting::Automatic;
+    camera_timer.timer.pause();
+    planet_timer.timer.pause();
+}
+
 // spawn a camera into the system
 fn add_camera(
     mut commands: Commands
 ) {
     commands.spawn((
         Camera3dBundle {
+            projection: PerspectiveProjection {
+                near: CAMERA_NEAR_PLANE,
+                ..default()
+            }.into(),
             transform: Transform::from_xyz(0.0, 0.0, 400.0)
                 .looking_at(Vec3::ZERO, Vec3::Y),
             ..default()
@@ -141,18 +264,26 @@ fn add_camera(
             orbit_angle: 0.0,
             orbit_speed: 1.0,
             orbit_distance: 5.0,
-            viewing_height: 5.0,
-            automatic_on: true
-        }
+            viewing_height: 5.0
+        },
+        ManualCameraController::default(),
+        CameraFlight { duration: CAMERA_FLIGHT_TIME, ..default() },
+        CameraFollow::default(),
+        MainCamera
     )).insert(Name::new("Camera"));
 }
 
 // create some camera controls
+// left alone while the full-screen map is open, as the main camera is hidden
 fn camera_controls(
     keyboard: Res<Input<KeyCode>>,
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    map_view: Res<MapView>,
     time: Res<Time>
 ) {
+    if map_view.enabled {
+        return;
+    }
     let mut camera = camera_query.single_mut();
     let mut direction_to_move: Vec3 = Vec3::ZERO;
 
@@ -177,9 +308,13 @@ fn camera_controls(
 
 fn camera_pan(
     keyboard: Res<Input<KeyCode>>,
-    mut camera_query: Query<&mut Transform, With<Camera3d>>,
+    mut camera_query: Query<&mut Transform, With<MainCamera>>,
+    map_view: Res<MapView>,
     time: Res<Time>
 ) {
+    if map_view.enabled {
+        return;
+    }
     let mut camera = camera_query.single_mut();
 
     if keyboard.pressed(KeyCode::Up) {
@@ -196,30 +331,126 @@ fn camera_pan(
     }
 } 
 
+// ui the pointer can be over instead of the scene
+type PointerCaptureFilter = Or<(With<Button>, With<InsetFrame>)>;
+
+/// Drag with the left mouse button to orbit the focus point, with the middle
+/// button to pan, and scroll to zoom. Panning and zooming scale with the
+/// distance to the focus point, so they feel the same around a moon as they
+/// do across the whole solar system. Only used by the manual camera.
+fn mouse_camera_controls(
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut camera_query: Query<(&mut Transform, &mut ManualCameraController), With<MainCamera>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    interaction_query: Query<&Interaction, PointerCaptureFilter>,
+    camera_setting: Res<CameraSetting>,
+    map_view: Res<MapView>
+) {
+    let drag: Vec2 = mouse_motion.iter().map(|motion| motion.delta).sum();
+    let scroll: f32 = mouse_wheel.iter()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / SCROLL_PIXELS_PER_LINE
+        })
+        .sum();
+    let Ok((mut camera, mut controller)) = camera_query.get_single_mut() else { return };
+    // dragging over the control panel works its buttons, and over the inset the inset, not the camera
+    let over_ui: bool = interaction_query.iter().any(|interaction| *interaction != Interaction::None);
+    if *camera_setting != CameraSetting::Manual || over_ui || map_view.enabled {
+        return;
+    }
+
+    let mut focus: Vec3 = camera.translation + camera.forward() * controller.focus_distance;
+    let mut moved: bool = false;
+    if mouse_buttons.pressed(MouseButton::Left) && drag != Vec2::ZERO {
+        // yaw around the world's up axis, pitch around the camera's own, stopping short of the poles
+        let yaw: Quat = Quat::from_rotation_y(-drag.x * MOUSE_ORBIT_SPEED);
+        let pitched: Quat = yaw * camera.rotation * Quat::from_rotation_x(-drag.y * MOUSE_ORBIT_SPEED);
+        camera.rotation = if (pitched * Vec3::NEG_Z).y.abs() < 0.99 { pitched } else { yaw * camera.rotation };
+        moved = true;
+    }
+    if mouse_buttons.pressed(MouseButton::Middle) && drag != Vec2::ZERO {
+        let pan_scale: f32 = controller.focus_distance * MOUSE_PAN_SPEED;
+        focus += (camera.left() * drag.x + camera.up() * drag.y) * pan_scale;
+        moved = true;
+    }
+    if scroll != 0.0 {
+        controller.focus_distance = (controller.focus_distance * (-scroll * SCROLL_ZOOM_SPEED).exp())
+            .clamp(MIN_FOCUS_DISTANCE, MAX_FOCUS_DISTANCE);
+        moved = true;
+    }
+    if moved {
+        camera.translation = focus - camera.forward() * controller.focus_distance;
+    }
+}
+
+/// Smooth start and stop for a flight, `t` from 0 to 1
+pub fn ease_in_out(t: f32) -> f32 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Where the camera is part way through `flight`, at `t` from 0 to 1, heading
+/// for `framing`, which looks at `target`. The point looked at moves straight
+/// across, while the distance to it is interpolated on a log scale and
+/// bulges out in the middle, so a long flight shows where it is going.
+fn flight_transform(flight: &CameraFlight, framing: &Transform, target: Vec3, t: f32) -> Transform {
+    let eased: f32 = ease_in_out(t);
+    let start_distance: f32 = flight.start_translation.distance(flight.start_focus).max(MIN_FOCUS_DISTANCE);
+    let end_distance: f32 = framing.translation.distance(target).max(MIN_FOCUS_DISTANCE);
+    let peak_distance: f32 = flight.start_focus.distance(target) * CAMERA_FLIGHT_ZOOM_OUT;
+    let zoom_out: f32 = (peak_distance.ln() - start_distance.max(end_distance).ln()).max(0.0);
+    let log_distance: f32 = start_distance.ln() * (1.0 - eased) + end_distance.ln() * eased
+        + zoom_out * (PI * t).sin();
+
+    let rotation: Quat = flight.start_rotation.slerp(framing.rotation, eased);
+    let focus: Vec3 = flight.start_focus.lerp(target, eased);
+    Transform {
+        translation: focus - rotation * Vec3::NEG_Z * log_distance.exp(),
+        rotation,
+        ..*framing
+    }
+}
+
+/// Orbit the current planet at its optimal distance, in radii of the planet
+/// as drawn, flying there first when the planet changes. The framing grows
+/// and shrinks with the planet while the display scale changes.
+#[allow(clippy::too_many_arguments)]
 fn automatic_camera(
-    mut camera_query: Query<(&mut Transform, &mut AutomaticCameraParameters), With<Camera3d>>,
-    solar_system_object_query: Query<(&Transform, &SolarSystemObjectData, &ObjectName), Without<Camera3d>>,
+    mut camera_query: Query<(&mut Transform, &mut AutomaticCameraParameters, &mut ManualCameraController, &mut CameraFlight), With<MainCamera>>,
+    mut framed_radius: Local<f32>,
+    solar_system_object_query: Query<(&Transform, &SolarSystemObjectData), Without<MainCamera>>,
     time: Res<Time>,
-    automatic_camera_planet: Res<CurrentPlanetForCamera>
+    automatic_camera_planet: Res<CurrentPlanetForCamera>,
+    camera_setting: Res<CameraSetting>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>,
+    tour: Res<GuidedTour>
 ) {
 
-    if let Ok((mut camera_transform, mut camera_parameters)) 
+    if let Ok((mut camera_transform, mut camera_parameters, mut manual_controller, mut flight)) 
     = camera_query.get_single_mut() {
-        if camera_parameters.automatic_on == false {
+        if *camera_setting != CameraSetting::Automatic {
+            // fly back in from wherever the camera is left
+            flight.target = None;
             return
         }
-        for (solar_system_object_transform,
-            solar_system_object_data,
-            object_name)
-        in &solar_system_object_query {
-            // if solar_system_object_data.name != "Earth".to_string() {
-            //     continue;
-            // }
-            if object_name.name != automatic_camera_planet.planet {
-                continue;
+        let Some(planet_entity) = automatic_camera_planet.body
+            .and_then(|id| registry.entity(id)) else { return };
+        if let Ok((solar_system_object_transform, solar_system_object_data))
+        = solar_system_object_query.get(planet_entity) {
+            let radius: f32 = display_scale.radius(solar_system_object_data.radius_m);
+            if tour.is_framing(automatic_camera_planet.body) {
+                // a tour stop sets its own framing every frame, sized to the body as drawn
+            } else if flight.target != Some(planet_entity) {
+                camera_parameters.orbit_distance = solar_system_object_data.optimal_camera_distance * radius;
+                camera_parameters.viewing_height = camera_parameters.orbit_distance * AUTOMATIC_CAMERA_HEIGHT;
+            } else if *framed_radius > 0.0 && radius != *framed_radius {
+                let growth: f32 = radius / *framed_radius;
+                camera_parameters.orbit_distance *= growth;
+                camera_parameters.viewing_height *= growth;
             }
-            camera_parameters.orbit_distance = solar_system_object_data.optimal_camera_distance;
-
+            *framed_radius = radius;
             let planet_coordinates: Vec3 = solar_system_object_transform.translation;
             let orbit_angle: f32 = camera_parameters.orbit_angle;
             let orbit_distance: f32 = camera_parameters.orbit_distance;
@@ -236,10 +467,28 @@ fn automatic_camera(
             let orbit_x_distance: f32 = orbit_distance * new_orbit_angle.cos();
             let orbit_z_distance: f32 = orbit_distance * new_orbit_angle.sin();
 
-            camera_transform.translation.x = planet_coordinates.x + orbit_x_distance; 
-            camera_transform.translation.z = planet_coordinates.z + orbit_z_distance;
-            camera_transform.translation.y = planet_coordinates.y + viewing_height;
-            camera_transform.look_at(planet_coordinates, Vec3::Y);
+            let framing: Transform = Transform::from_xyz(
+                planet_coordinates.x + orbit_x_distance,
+                planet_coordinates.y + viewing_height,
+                planet_coordinates.z + orbit_z_distance
+            ).looking_at(planet_coordinates, Vec3::Y);
+
+            if flight.target != Some(planet_entity) {
+                flight.target = Some(planet_entity);
+                flight.elapsed = 0.0;
+                flight.start_translation = camera_transform.translation;
+                flight.start_rotation = camera_transform.rotation;
+                flight.start_focus = camera_transform.translation +
+                    camera_transform.forward() * manual_controller.focus_distance;
+            }
+            flight.elapsed += time.delta_seconds();
+            *camera_transform = if flight.elapsed < flight.duration {
+                flight_transform(&flight, &framing, planet_coordinates, flight.elapsed / flight.duration)
+            } else {
+                framing
+            };
+            // switching to manual then orbits the same planet
+            manual_controller.focus_distance = camera_transform.translation.distance(planet_coordinates);
         }
     }
 }
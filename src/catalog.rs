@@ -0,0 +1,706 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use bevy::asset::FileAssetIo;
+use bevy::math::DVec3;
+use serde::Deserialize;
+use serde_path_to_error::Segment;
+
+use crate::nbody::{
+    ecliptic_to_world, move_to_barycentric_frame, parse_horizons_vectors,
+    Body, HorizonsVectors, OrbitalElements, StateRecord, GRAV_CONST, J2000_JULIAN_DATE, SECONDS_PER_DAY
+};
+use crate::{BodyCategory, SolarSystemObjectData};
+
+pub const BODY_CATALOG_FILE: &str = "solar_system.ron";
+// Horizons prints Julian dates to 9 decimal places
+const EPHEMERIS_EPOCH_TOLERANCE_DAYS: f64 = 1e-6;
+
+// THE WHOLE CATALOG FILE
+#[derive(Deserialize)]
+struct RawBodyCatalog {
+    #[serde(default = "default_epoch")]
+    epoch: f64,
+    #[serde(default)]
+    ephemeris: Option<String>,
+    bodies: Vec<BodyDefinition>
+}
+
+// the catalog with each body kept as a raw value, read first so syntax errors
+// keep their position and a body that fails to deserialize can be reported by name
+#[derive(Deserialize)]
+struct RawBodyEntries {
+    bodies: Vec<ron::Value>
+}
+
+fn default_epoch() -> f64 {
+    J2000_JULIAN_DATE
+}
+
+/// Every body in the catalog, with the Julian date their starting states describe.
+/// `ephemeris` is an optional folder of JPL Horizons vector tables, relative to
+/// the catalog file, whose states replace the catalog's own for matching bodies.
+#[derive(Debug, Clone)]
+pub struct BodyCatalog {
+    pub epoch: f64,
+    pub ephemeris: Option<String>,
+    pub bodies: Vec<BodyDefinition>,
+    // absolute states taken from Horizons tables, keyed by body name
+    imported_states: HashMap<String, (DVec3, DVec3)>
+}
+
+/// One solar system object as written in the catalog. Physical values are
+/// in SI units, `model` is a path under `assets/` and `model_radius` is the
+/// radius of the body's sphere in the model's own units, so it can be drawn
+/// at `radius_m`. `optimal_camera_distance` is in radii of the body as drawn.
+/// When `parent` names another body,
+/// the starting state is relative to that body, and can be given either as
+/// `position` and `speed` or as an `orbit` around the parent.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct BodyDefinition {
+    pub name: String,
+    #[serde(default)]
+    pub category: BodyCategory,
+    #[serde(default)]
+    pub parent: Option<String>,
+    pub mass_kg: f64,
+    #[serde(default)]
+    pub radius_m: f64,
+    #[serde(default)]
+    pub position: [f64; 3],
+    #[serde(default)]
+    pub speed: [f64; 3],
+    #[serde(default)]
+    pub orbit: Option<OrbitDefinition>,
+    #[serde(default)]
+    pub spin: f64,
+    #[serde(default)]
+    pub tilt: f32,
+    pub model: String,
+    #[serde(default = "default_model_radius")]
+    pub model_radius: f32,
+    #[serde(default = "default_camera_distance")]
+    pub optimal_camera_distance: f32
+}
+
+fn default_model_radius() -> f32 {
+    1.0
+}
+
+fn default_camera_distance() -> f32 {
+    4.0
+}
+
+/// Keplerian elements of a body around its parent. The semi-major axis is in
+/// metres (negative for a hyperbolic orbit) and angles are in degrees against
+/// the ecliptic. `epoch` is the Julian date the mean anomaly applies to,
+/// defaulting to the catalog's epoch.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct OrbitDefinition {
+    pub semi_major_axis: f64,
+    pub eccentricity: f64,
+    #[serde(default)]
+    pub inclination: f64,
+    #[serde(default)]
+    pub longitude_of_ascending_node: f64,
+    #[serde(default)]
+    pub argument_of_periapsis: f64,
+    #[serde(default)]
+    pub mean_anomaly: f64,
+    #[serde(default)]
+    pub epoch: Option<f64>
+}
+
+impl OrbitDefinition {
+    pub fn elements(&self) -> OrbitalElements {
+        OrbitalElements {
+            semi_major_axis_m: self.semi_major_axis,
+            eccentricity: self.eccentricity,
+            inclination: self.inclination.to_radians(),
+            longitude_of_ascending_node: self.longitude_of_ascending_node.to_radians(),
+            argument_of_periapsis: self.argument_of_periapsis.to_radians(),
+            mean_anomaly: self.mean_anomaly.to_radians()
+        }
+    }
+
+    fn validate(&self) -> Result<(), (&'static str, &'static str)> {
+        let angles: [f64; 4] = [
+            self.inclination,
+            self.longitude_of_ascending_node,
+            self.argument_of_periapsis,
+            self.mean_anomaly
+        ];
+        if !(self.eccentricity.is_finite() && self.eccentricity >= 0.0) {
+            return Err(("orbit.eccentricity", "must be zero or more"));
+        }
+        if self.eccentricity == 1.0 {
+            return Err(("orbit.eccentricity", "parabolic orbits are not supported"));
+        }
+        if !self.semi_major_axis.is_finite() || self.semi_major_axis == 0.0 {
+            return Err(("orbit.semi_major_axis", "must be a non-zero number"));
+        }
+        if (self.eccentricity < 1.0) != (self.semi_major_axis > 0.0) {
+            return Err(("orbit.semi_major_axis",
+                "must be positive for a closed orbit and negative for a hyperbolic one"));
+        }
+        if !angles.iter().all(|angle| angle.is_finite()) {
+            return Err(("orbit", "angles must be finite numbers"));
+        }
+        if !self.epoch.is_none_or(f64::is_finite) {
+            return Err(("orbit.epoch", "must be a finite Julian date"));
+        }
+        Ok(())
+    }
+}
+impl BodyCatalog {
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.bodies.iter().position(|body| body.name == name)
+    }
+
+    /// The starting state of every body, in catalog order. Parent-relative
+    /// states are added onto their parent's, stopping at the first body with
+    /// an imported ephemeris state, then the whole system is moved into the
+    /// barycentric frame.
+    pub fn initial_bodies(&self) -> Vec<Body> {
+        let relative_states: Vec<(DVec3, DVec3)> = self.bodies
+            .iter()
+            .map(|body| self.relative_state(body))
+            .collect();
+        let mut bodies: Vec<Body> = (0..self.bodies.len())
+            .map(|index| {
+                let definition: &BodyDefinition = &self.bodies[index];
+                let mut position: DVec3 = DVec3::ZERO;
+                let mut velocity: DVec3 = DVec3::ZERO;
+                // parse_body_catalog has already ruled out unknown parents and cycles
+                let mut current: Option<usize> = Some(index);
+                while let Some(current_index) = current {
+                    if let Some((imported_position, imported_velocity)) =
+                        self.imported_states.get(&self.bodies[current_index].name) {
+                        position += *imported_position;
+                        velocity += *imported_velocity;
+                        break;
+                    }
+                    position += relative_states[current_index].0;
+                    velocity += relative_states[current_index].1;
+                    current = self.bodies[current_index].parent.as_deref()
+                        .and_then(|parent| self.index_of(parent));
+                }
+                Body::new(definition.name.clone(), definition.mass_kg, position, velocity)
+            })
+            .collect();
+        move_to_barycentric_frame(&mut bodies);
+        bodies
+    }
+
+    // a body's starting position and velocity from the catalog alone, summed up
+    // through its parents and ignoring any imported states
+    fn catalog_state(&self, index: usize) -> (DVec3, DVec3) {
+        let mut state: (DVec3, DVec3) = (DVec3::ZERO, DVec3::ZERO);
+        let mut current: Option<usize> = Some(index);
+        while let Some(current_index) = current {
+            let (position, velocity) = self.relative_state(&self.bodies[current_index]);
+            state = (state.0 + position, state.1 + velocity);
+            current = self.bodies[current_index].parent.as_deref()
+                .and_then(|parent| self.index_of(parent));
+        }
+        state
+    }
+
+    // a body's starting position and velocity relative to its parent (or the origin)
+    fn relative_state(&self, body: &BodyDefinition) -> (DVec3, DVec3) {
+        let parent: Option<&BodyDefinition> = body.parent.as_deref()
+            .and_then(|parent| self.index_of(parent))
+            .map(|index| &self.bodies[index]);
+        match (&body.orbit, parent) {
+            (Some(orbit), Some(parent)) => {
+                let mu: f64 = GRAV_CONST * (parent.mass_kg + body.mass_kg);
+                let elapsed_seconds: f64 =
+                    (self.epoch - orbit.epoch.unwrap_or(self.epoch)) * SECONDS_PER_DAY;
+                let (position, velocity) = orbit.elements()
+                    .propagated(mu, elapsed_seconds)
+                    .to_state_vectors(mu);
+                (ecliptic_to_world(position), ecliptic_to_world(velocity))
+            }
+            _ => (DVec3::from(body.position), DVec3::from(body.speed))
+        }
+    }
+
+    /// Take the starting state of each body with a Horizons table from that
+    /// table's record at the catalog epoch. Tables are matched to bodies by
+    /// their target name, ignoring case, and must all share one center: either
+    /// the solar system barycenter or a body in the catalog. States measured
+    /// from a body are added onto that body's own catalog state, so they line
+    /// up with the bodies that keep theirs.
+    pub fn apply_ephemeris(&mut self, tables: &[(PathBuf, HorizonsVectors)]) -> Result<(), CatalogError> {
+        let invalid = |path: &Path, message: String| CatalogError::Ephemeris {
+            path: path.to_path_buf(),
+            message
+        };
+
+        let mut center_name: Option<&str> = None;
+        let mut imported_states: HashMap<String, (DVec3, DVec3)> = HashMap::new();
+        for (path, table) in tables {
+            match center_name {
+                Some(center_name) if !center_name.eq_ignore_ascii_case(&table.center_name) =>
+                    return Err(invalid(path, format!(
+                        "centered on '{}' but other tables are centered on '{}'",
+                        table.center_name, center_name
+                    ))),
+                _ => center_name = Some(&table.center_name)
+            }
+            if !table.is_barycentric() && self.find_name(&table.center_name).is_none() {
+                return Err(invalid(path, format!(
+                    "center body '{}' is not in the catalog", table.center_name
+                )));
+            }
+
+            let Some(name) = self.find_name(&table.target_name) else {
+                return Err(invalid(path, format!("there is no body named '{}'", table.target_name)));
+            };
+            let record: &StateRecord = table
+                .record_at(self.epoch, EPHEMERIS_EPOCH_TOLERANCE_DAYS)
+                .ok_or_else(|| invalid(path, format!("has no state for Julian date {}", self.epoch)))?;
+            let state = (ecliptic_to_world(record.position), ecliptic_to_world(record.velocity));
+            if imported_states.insert(name.to_string(), state).is_some() {
+                return Err(invalid(path, format!("'{}' already has an ephemeris table", name)));
+            }
+        }
+
+        let center_index: Option<usize> = center_name
+            .and_then(|name| self.find_name(name))
+            .and_then(|name| self.index_of(name));
+        if let Some(center_index) = center_index {
+            let (center_position, center_velocity) = self.catalog_state(center_index);
+            for (position, velocity) in imported_states.values_mut() {
+                *position += center_position;
+                *velocity += center_velocity;
+            }
+        }
+        self.imported_states = imported_states;
+        Ok(())
+    }
+
+    // the catalog spelling of a name from another source
+    fn find_name(&self, name: &str) -> Option<&str> {
+        self.bodies.iter()
+            .find(|body| body.name.eq_ignore_ascii_case(name))
+            .map(|body| body.name.as_str())
+    }
+
+    // every parent must be another body in the catalog, without loops
+    fn validate_parents(&self) -> Result<(), CatalogError> {
+        for body in &self.bodies {
+            let mut ancestors: HashSet<&str> = HashSet::from([body.name.as_str()]);
+            let mut parent: Option<&str> = body.parent.as_deref();
+            while let Some(parent_name) = parent {
+                let Some(parent_index) = self.index_of(parent_name) else {
+                    return Err(CatalogError::InvalidBody {
+                        body: body.name.clone(),
+                        field: "parent".to_string(),
+                        message: format!("there is no body named '{}'", parent_name)
+                    });
+                };
+                if !ancestors.insert(parent_name) {
+                    return Err(CatalogError::InvalidBody {
+                        body: body.name.clone(),
+                        field: "parent".to_string(),
+                        message: format!("'{}' is its own ancestor", parent_name)
+                    });
+                }
+                parent = self.bodies[parent_index].parent.as_deref();
+            }
+        }
+        Ok(())
+    }
+}
+
+impl BodyDefinition {
+    /// Component data for this body, starting from `state`
+    /// (one of `BodyCatalog::initial_bodies`)
+    pub fn object_data(&self, state: &Body) -> SolarSystemObjectData {
+        SolarSystemObjectData {
+            name: self.name.clone(),
+            mass_kg: self.mass_kg,
+            radius_m: self.radius_m,
+            position_x: state.position.x,
+            position_y: state.position.y,
+            position_z: state.position.z,
+            speed_x: state.velocity.x,
+            speed_y: state.velocity.y,
+            speed_z: state.velocity.z,
+            acceleration_x: 0.0,
+            acceleration_y: 0.0,
+            acceleration_z: 0.0,
+            spin: self.spin,
+            tilt: self.tilt,
+            model_radius: self.model_radius,
+            optimal_camera_distance: self.optimal_camera_distance
+        }
+    }
+
+    // catch values that parse fine but make no physical sense
+    fn validate(&self) -> Result<(), CatalogError> {
+        let invalid = |field: &str, message: &str| CatalogError::InvalidBody {
+            body: self.name.clone(),
+            field: field.to_string(),
+            message: message.to_string()
+        };
+
+        if self.name.trim().is_empty() {
+            return Err(invalid("name", "must not be empty"));
+        }
+        if !(self.mass_kg.is_finite() && self.mass_kg > 0.0) {
+            return Err(invalid("mass_kg", "must be a positive number"));
+        }
+        if !(self.radius_m.is_finite() && self.radius_m >= 0.0) {
+            return Err(invalid("radius_m", "must not be negative"));
+        }
+        if !self.position.iter().all(|value| value.is_finite()) {
+            return Err(invalid("position", "must only contain finite numbers"));
+        }
+        if !self.speed.iter().all(|value| value.is_finite()) {
+            return Err(invalid("speed", "must only contain finite numbers"));
+        }
+        if !(self.model_radius.is_finite() && self.model_radius > 0.0) {
+            return Err(invalid("model_radius", "must be a positive number"));
+        }
+        if !(self.optimal_camera_distance.is_finite() && self.optimal_camera_distance > 0.0) {
+            return Err(invalid("optimal_camera_distance", "must be a positive number"));
+        }
+        if self.model.trim().is_empty() {
+            return Err(invalid("model", "must not be empty"));
+        }
+        if let Some(orbit) = &self.orbit {
+            if self.parent.is_none() {
+                return Err(invalid("orbit", "needs a parent body to orbit"));
+            }
+            if self.position != [0.0; 3] {
+                return Err(invalid("position", "cannot be given alongside an orbit"));
+            }
+            if self.speed != [0.0; 3] {
+                return Err(invalid("speed", "cannot be given alongside an orbit"));
+            }
+            orbit.validate().map_err(|(field, message)| invalid(field, message))?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    Io { path: PathBuf, error: std::io::Error },
+    Syntax(ron::error::SpannedError),
+    InvalidBody { body: String, field: String, message: String },
+    DuplicateBody(String),
+    InvalidEpoch(f64),
+    Ephemeris { path: PathBuf, message: String }
+}
+
+impl fmt::Display for CatalogError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CatalogError::Io { path, error } =>
+                write!(f, "could not read body catalog {}: {}", path.display(), error),
+            CatalogError::Syntax(error) =>
+                write!(f, "body catalog is not valid RON: {}", error),
+            CatalogError::InvalidBody { body, field, message } if field.is_empty() =>
+                write!(f, "body '{}': {}", body, message),
+            CatalogError::InvalidBody { body, field, message } =>
+                write!(f, "body '{}', field '{}': {}", body, field, message),
+            CatalogError::DuplicateBody(body) =>
+                write!(f, "body '{}' is listed more than once", body),
+            CatalogError::InvalidEpoch(epoch) =>
+                write!(f, "catalog epoch {} is not a valid Julian date", epoch),
+            CatalogError::Ephemeris { path, message } =>
+                write!(f, "ephemeris table {}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for CatalogError {}
+
+/// Path of a file under the same `assets/` folder the `AssetServer` reads from
+pub fn asset_path(file: impl AsRef<Path>) -> PathBuf {
+    FileAssetIo::get_base_path().join("assets").join(file)
+}
+
+pub fn load_body_catalog(path: impl AsRef<Path>) -> Result<BodyCatalog, CatalogError> {
+    let path: &Path = path.as_ref();
+    let text: String = std::fs::read_to_string(path)
+        .map_err(|error| CatalogError::Io { path: path.to_path_buf(), error })?;
+    let mut catalog: BodyCatalog = parse_body_catalog(&text)?;
+    if let Some(ephemeris) = catalog.ephemeris.clone() {
+        let directory: PathBuf = path.parent().unwrap_or(Path::new("")).join(ephemeris);
+        catalog.apply_ephemeris(&load_ephemeris_tables(&directory)?)?;
+    }
+    Ok(catalog)
+}
+
+/// Read every Horizons vector table (`.txt` or `.csv`) in `directory`
+pub fn load_ephemeris_tables(directory: &Path) -> Result<Vec<(PathBuf, HorizonsVectors)>, CatalogError> {
+    let io_error = |error: std::io::Error| CatalogError::Io { path: directory.to_path_buf(), error };
+    let mut paths: Vec<PathBuf> = std::fs::read_dir(directory)
+        .map_err(io_error)?
+        .map(|entry| entry.map(|entry| entry.path()))
+        .collect::<Result<_, _>>()
+        .map_err(io_error)?;
+    paths.retain(|path| {
+        path.extension().is_some_and(|extension| extension == "txt" || extension == "csv")
+    });
+    // sorted so a clash between two tables is always reported the same way
+    paths.sort();
+
+    paths.into_iter()
+        .map(|path| {
+            let text: String = std::fs::read_to_string(&path)
+                .map_err(|error| CatalogError::Io { path: path.clone(), error })?;
+            let table: HorizonsVectors = parse_horizons_vectors(&text)
+                .map_err(|error| CatalogError::Ephemeris { path: path.clone(), message: error.to_string() })?;
+            Ok((path, table))
+        })
+        .collect()
+}
+
+pub fn parse_body_catalog(text: &str) -> Result<BodyCatalog, CatalogError> {
+    let entries: RawBodyEntries = ron::from_str(text).map_err(CatalogError::Syntax)?;
+
+    let mut deserializer = ron::Deserializer::from_str(text).map_err(CatalogError::Syntax)?;
+    let raw_catalog: RawBodyCatalog = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|error| {
+            let mut segments = error.path().iter();
+            match (segments.next(), segments.next()) {
+                (Some(Segment::Map { key }), Some(Segment::Seq { index })) if key == "bodies" => {
+                    let body_name: String = entries.bodies.get(*index)
+                        .and_then(raw_body_name)
+                        .unwrap_or_else(|| format!("#{}", index + 1));
+                    CatalogError::InvalidBody {
+                        body: body_name,
+                        // a missing field is reported against the body itself
+                        field: field_path(segments),
+                        message: error.into_inner().to_string()
+                    }
+                }
+                _ => CatalogError::Syntax(deserializer.span_error(error.into_inner()))
+            }
+        })?;
+    deserializer.end().map_err(|error| CatalogError::Syntax(deserializer.span_error(error)))?;
+
+    let mut names: HashSet<&str> = HashSet::new();
+    for body in &raw_catalog.bodies {
+        body.validate()?;
+        if !names.insert(&body.name) {
+            return Err(CatalogError::DuplicateBody(body.name.clone()));
+        }
+    }
+    if !raw_catalog.epoch.is_finite() {
+        return Err(CatalogError::InvalidEpoch(raw_catalog.epoch));
+    }
+    let catalog: BodyCatalog = BodyCatalog {
+        epoch: raw_catalog.epoch,
+        ephemeris: raw_catalog.ephemeris,
+        bodies: raw_catalog.bodies,
+        imported_states: HashMap::new()
+    };
+    catalog.validate_parents()?;
+    Ok(catalog)
+}
+
+// the name of a body that has not been checked yet, if it has a usable one
+fn raw_body_name(value: &ron::Value) -> Option<String> {
+    let ron::Value::Map(fields) = value else { return None };
+    fields.iter().find_map(|(key, field)| match (key, field) {
+        (ron::Value::String(key), ron::Value::String(name)) if key == "name" => Some(name.clone()),
+        _ => None
+    })
+}
+
+// the rest of a serde path, written the way serde_path_to_error writes a whole one
+fn field_path<'a>(segments: impl Iterator<Item = &'a Segment>) -> String {
+    let mut field: String = String::new();
+    for segment in segments {
+        if !field.is_empty() && !matches!(segment, Segment::Seq { .. }) {
+            field.push('.');
+        }
+        field.push_str(&segment.to_string());
+    }
+    field
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use bevy::math::DVec3;
+
+    use super::*;
+    use crate::nbody::{HorizonsVectors, StateRecord};
+
+    // a Sun away from the origin, so an imported state measured from the wrong place shows up
+    const CATALOG: &str = r#"(
+        epoch: 2451545.0,
+        bodies: [
+            (name: "Sun", mass_kg: 1.989e30, position: (3.0e9, -1.0e9, 2.0e8), speed: (5.0, 1.0, 0.0), model: "sun.glb"),
+            (
+                name: "Earth",
+                parent: Some("Sun"),
+                mass_kg: 5.972e24,
+                orbit: Some((semi_major_axis: 1.496e11, eccentricity: 0.0167, mean_anomaly: 357.5)),
+                model: "earth.glb"
+            ),
+            (
+                name: "Moon",
+                parent: Some("Earth"),
+                mass_kg: 7.342e22,
+                position: (3.844e8, 0.0, 0.0),
+                speed: (0.0, 0.0, -1022.0),
+                model: "moon.glb"
+            ),
+        ],
+    )"#;
+
+    fn table(target: &str, center: &str, position: DVec3, velocity: DVec3) -> (PathBuf, HorizonsVectors) {
+        (
+            PathBuf::from(format!("{target}.txt")),
+            HorizonsVectors {
+                target_name: target.to_string(),
+                center_name: center.to_string(),
+                records: vec![StateRecord { julian_date: 2451545.0, position, velocity }]
+            }
+        )
+    }
+
+    fn body<'a>(bodies: &'a [Body], name: &str) -> &'a Body {
+        bodies.iter().find(|body| body.name == name).unwrap()
+    }
+
+    #[test]
+    fn states_from_a_center_body_are_placed_around_it() {
+        let mut catalog: BodyCatalog = parse_body_catalog(CATALOG).unwrap();
+        // measured from Earth, in the ecliptic frame
+        let moon_position: DVec3 = DVec3::new(-2.9e8, 2.6e8, 1.2e7);
+        let moon_velocity: DVec3 = DVec3::new(-640.0, -710.0, 60.0);
+        catalog.apply_ephemeris(&[table("moon", "Earth", moon_position, moon_velocity)]).unwrap();
+
+        let bodies: Vec<Body> = catalog.initial_bodies();
+        let (position, velocity) = body(&bodies, "Moon").relative_to(body(&bodies, "Earth"));
+        assert!(position.distance(ecliptic_to_world(moon_position)) < 1e-3, "Moon at {position} from Earth");
+        assert!(velocity.distance(ecliptic_to_world(moon_velocity)) < 1e-9, "Moon at {velocity} from Earth");
+    }
+
+    #[test]
+    fn barycentric_states_are_taken_as_they_are() {
+        let mut catalog: BodyCatalog = parse_body_catalog(CATALOG).unwrap();
+        let earth_position: DVec3 = DVec3::new(-2.5e10, 1.45e11, -6.0e5);
+        let earth_velocity: DVec3 = DVec3::new(-2.98e4, -5.2e3, 0.06);
+        let sun_position: DVec3 = DVec3::new(-1.07e9, -3.6e8, 2.5e7);
+        let sun_velocity: DVec3 = DVec3::new(9.3, -12.8, -0.16);
+        catalog.apply_ephemeris(&[
+            table("Earth", "Solar System Barycenter", earth_position, earth_velocity),
+            table("Sun", "Solar System Barycenter", sun_position, sun_velocity)
+        ]).unwrap();
+
+        let bodies: Vec<Body> = catalog.initial_bodies();
+        let (position, _) = body(&bodies, "Earth").relative_to(body(&bodies, "Sun"));
+        assert!(position.distance(ecliptic_to_world(earth_position - sun_position)) < 1e-3);
+        // the Moon keeps its catalog state around the imported Earth
+        let (moon_position, _) = body(&bodies, "Moon").relative_to(body(&bodies, "Earth"));
+        assert!(moon_position.distance(DVec3::new(3.844e8, 0.0, 0.0)) < 1e-3);
+    }
+
+    #[test]
+    fn tables_must_share_one_known_center() {
+        let mut catalog: BodyCatalog = parse_body_catalog(CATALOG).unwrap();
+        let mixed: Result<(), CatalogError> = catalog.apply_ephemeris(&[
+            table("Earth", "Sun", DVec3::X, DVec3::Y),
+            table("Moon", "Earth", DVec3::X, DVec3::Y)
+        ]);
+        assert!(matches!(mixed, Err(CatalogError::Ephemeris { .. })));
+        let unknown: Result<(), CatalogError> = catalog.apply_ephemeris(&[table("Earth", "Vulcan", DVec3::X, DVec3::Y)]);
+        assert!(matches!(unknown, Err(CatalogError::Ephemeris { .. })));
+    }
+
+    #[test]
+    fn shipped_catalog_loads_with_its_categories() {
+        let catalog: BodyCatalog = load_body_catalog(asset_path(BODY_CATALOG_FILE)).unwrap();
+        let category = |name: &str| catalog.bodies[catalog.index_of(name).unwrap()].category;
+        assert_eq!(category("Sun"), BodyCategory::Star);
+        assert_eq!(category("Moon"), BodyCategory::Moon);
+        assert_eq!(category("Earth"), BodyCategory::Planet);
+    }
+
+    // a catalog of the given body entries, each written as the inside of a RON struct
+    fn catalog_of(bodies: &[&str]) -> String {
+        let entries: Vec<String> = bodies.iter().map(|body| format!("({body})")).collect();
+        format!("(bodies: [{}])", entries.join(", "))
+    }
+
+    fn invalid_field(result: Result<BodyCatalog, CatalogError>) -> (String, String, String) {
+        match result {
+            Err(CatalogError::InvalidBody { body, field, message }) => (body, field, message),
+            other => panic!("expected an invalid body, got {other:?}")
+        }
+    }
+
+    #[test]
+    fn malformed_fields_are_reported_by_body_and_path() {
+        let (body, field, message) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Sun", mass_kg: 1.989e30, model: "sun.glb""#,
+            r#"name: "Vesta", parent: Some("Sun"), model: "vesta.glb""#
+        ])));
+        assert_eq!((body.as_str(), field.as_str()), ("Vesta", ""));
+        assert!(message.contains("mass_kg"), "{message}");
+
+        let (body, field, _) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Sun", mass_kg: 1.989e30, model: "sun.glb""#,
+            r#"name: "Ceres", parent: Some("Sun"), mass_kg: 9.4e20, model: "ceres.glb",
+               orbit: Some((semi_major_axis: "far", eccentricity: 0.08))"#
+        ])));
+        assert_eq!((body.as_str(), field.as_str()), ("Ceres", "orbit.semi_major_axis"));
+    }
+
+    #[test]
+    fn mass_and_radius_must_make_sense() {
+        let (body, field, _) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Sun", mass_kg: 0.0, model: "sun.glb""#
+        ])));
+        assert_eq!((body.as_str(), field.as_str()), ("Sun", "mass_kg"));
+
+        let (_, field, _) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Sun", mass_kg: -1.989e30, model: "sun.glb""#
+        ])));
+        assert_eq!(field, "mass_kg");
+
+        let (body, field, _) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Sun", mass_kg: 1.989e30, radius_m: -6.957e8, model: "sun.glb""#
+        ])));
+        assert_eq!((body.as_str(), field.as_str()), ("Sun", "radius_m"));
+    }
+
+    #[test]
+    fn parents_must_exist_without_loops() {
+        let (body, field, message) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Moon", parent: Some("Vulcan"), mass_kg: 7.342e22, model: "moon.glb""#
+        ])));
+        assert_eq!((body.as_str(), field.as_str()), ("Moon", "parent"));
+        assert!(message.contains("Vulcan"), "{message}");
+
+        let (_, field, message) = invalid_field(parse_body_catalog(&catalog_of(&[
+            r#"name: "Earth", parent: Some("Moon"), mass_kg: 5.972e24, model: "earth.glb""#,
+            r#"name: "Moon", parent: Some("Earth"), mass_kg: 7.342e22, model: "moon.glb""#
+        ])));
+        assert_eq!(field, "parent");
+        assert!(message.contains("own ancestor"), "{message}");
+    }
+
+    #[test]
+    fn names_must_be_unique() {
+        let duplicate: Result<BodyCatalog, CatalogError> = parse_body_catalog(&catalog_of(&[
+            r#"name: "Earth", mass_kg: 5.972e24, model: "earth.glb""#,
+            r#"name: "Earth", mass_kg: 5.972e24, model: "earth.glb""#
+        ]));
+        assert!(matches!(duplicate, Err(CatalogError::DuplicateBody(name)) if name == "Earth"));
+    }
+}
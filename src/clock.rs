@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::nbody::{julian_date_to_tdb_seconds, tdb_seconds_to_julian_date, CalendarDate, SECONDS_PER_DAY};
+use crate::{describe_time_warp, describe_warp_speed, PhysicsClock, TimeWarp, WINDOW_TITLE};
+
+pub const SIMULATION_DATE_DISPLAY_TIME: f32 = 0.25;
+pub const WARP_LIMIT_TOLERANCE: f64 = 0.99; // of the warp, below which it is shown as held back
+
+// THE SINGLE SOURCE OF SIMULATED TIME
+// TDB seconds since J2000, moved on by every physics step
+#[derive(Reflect, Resource, Default, Debug)]
+pub struct SimulationClock {
+    pub tdb_seconds_since_j2000: f64
+}
+
+impl SimulationClock {
+    pub fn from_julian_date(julian_date: f64) -> SimulationClock {
+        SimulationClock { tdb_seconds_since_j2000: julian_date_to_tdb_seconds(julian_date) }
+    }
+
+    pub fn julian_date(&self) -> f64 {
+        tdb_seconds_to_julian_date(self.tdb_seconds_since_j2000)
+    }
+
+    pub fn set_julian_date(&mut self, julian_date: f64) {
+        self.tdb_seconds_since_j2000 = julian_date_to_tdb_seconds(julian_date);
+    }
+
+    pub fn days_since_j2000(&self) -> f64 {
+        self.tdb_seconds_since_j2000 / SECONDS_PER_DAY
+    }
+
+    pub fn utc(&self) -> CalendarDate {
+        CalendarDate::from_tdb_seconds(self.tdb_seconds_since_j2000)
+    }
+
+    pub fn set_utc(&mut self, date: &CalendarDate) {
+        self.tdb_seconds_since_j2000 = date.to_tdb_seconds();
+    }
+
+    /// Move the clock on by `seconds`, which is negative when time runs backwards
+    pub fn advance(&mut self, seconds: f64) {
+        self.tdb_seconds_since_j2000 += seconds;
+    }
+}
+
+#[derive(Resource)]
+struct SimulationDateDisplayTimer(Timer);
+
+pub struct SimulationClockPlugin;
+
+impl Plugin for SimulationClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<SimulationClock>()
+            .init_resource::<SimulationClock>()
+            .insert_resource(SimulationDateDisplayTimer(
+                Timer::from_seconds(SIMULATION_DATE_DISPLAY_TIME, TimerMode::Repeating)
+            ))
+            .add_system(show_simulation_date);
+    }
+}
+
+/// Keep the simulated UTC date and the time warp in the window title, along
+/// with the speed physics can actually keep up when that is slower
+fn show_simulation_date(
+    mut windows: ResMut<Windows>,
+    mut display_timer: ResMut<SimulationDateDisplayTimer>,
+    clock: Res<SimulationClock>,
+    time_warp: Res<TimeWarp>,
+    physics_clock: Res<PhysicsClock>,
+    time: Res<Time>
+) {
+    display_timer.0.tick(time.delta());
+    if !display_timer.0.just_finished() {
+        return;
+    }
+    let limited: bool = !time_warp.paused
+        && physics_clock.achieved_warp < WARP_LIMIT_TOLERANCE * time_warp.factor.abs();
+    let achieved: String = if limited {
+        format!(" (running at {})", describe_warp_speed(physics_clock.achieved_warp))
+    } else {
+        String::new()
+    };
+    if let Some(window) = windows.get_primary_mut() {
+        window.set_title(format!(
+            "{} - {} - {}{}", WINDOW_TITLE, clock.utc(), describe_time_warp(&time_warp), achieved
+        ));
+    }
+}
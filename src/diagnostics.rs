@@ -0,0 +1,148 @@
+use bevy::prelude::*;
+
+use crate::nbody::ConservedQuantities;
+use crate::{NBodySimulation, PhysicsBodiesEdited, PhysicsStage, PhysicsSystem, SimulationClock};
+
+pub const CONSERVATION_LOG_TIME: f32 = 10.0;
+pub const CONSERVATION_DRIFT_THRESHOLD: f64 = 1e-6;
+
+#[derive(Reflect, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConservedQuantity {
+    Energy,
+    LinearMomentum,
+    AngularMomentum
+}
+
+// RESOURCE HOLDING THE LATEST CONSERVATION FIGURES
+// drifts are relative to the values when the bodies were last registered or edited
+#[derive(Resource, Default)]
+pub struct ConservationDiagnostics {
+    pub initial: Option<ConservedQuantities>,
+    pub current: ConservedQuantities,
+    pub body_count: usize,
+    pub energy_drift: f64,
+    pub linear_momentum_drift: f64,
+    pub angular_momentum_drift: f64
+}
+
+impl ConservationDiagnostics {
+    pub fn drift(&self, quantity: ConservedQuantity) -> f64 {
+        match quantity {
+            ConservedQuantity::Energy => self.energy_drift,
+            ConservedQuantity::LinearMomentum => self.linear_momentum_drift,
+            ConservedQuantity::AngularMomentum => self.angular_momentum_drift,
+        }
+    }
+}
+
+// SETTINGS FOR WHEN TO LOG AND WARN ABOUT DRIFT
+#[derive(Reflect, Resource)]
+pub struct ConservationSettings {
+    pub drift_warning_threshold: f64,
+    pub log_timer: Timer
+}
+
+impl Default for ConservationSettings {
+    fn default() -> ConservationSettings {
+        ConservationSettings {
+            drift_warning_threshold: CONSERVATION_DRIFT_THRESHOLD,
+            log_timer: Timer::from_seconds(CONSERVATION_LOG_TIME, TimerMode::Repeating)
+        }
+    }
+}
+
+// sent once each time a quantity's drift rises past the warning threshold
+pub struct ConservationDriftWarning {
+    pub quantity: ConservedQuantity,
+    pub relative_drift: f64
+}
+
+pub struct ConservationDiagnosticsPlugin;
+
+impl Plugin for ConservationDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConservationDiagnostics>()
+            .register_type::<ConservationSettings>()
+            .init_resource::<ConservationSettings>()
+            .add_event::<ConservationDriftWarning>()
+            .add_system_to_stage(PhysicsStage::Step, measure_conservation
+                .after(PhysicsSystem::Move))
+            .add_system(log_conservation_diagnostics)
+            .add_system(log_conservation_warnings);
+    }
+}
+
+/// Measure the conserved quantities after every physics step and work out
+/// how far they have drifted. Adding, removing or editing bodies changes the
+/// totals, so the reference values are retaken whenever the body count
+/// changes or bodies are edited.
+fn measure_conservation(
+    mut diagnostics: ResMut<ConservationDiagnostics>,
+    mut warning_writer: EventWriter<ConservationDriftWarning>,
+    mut edited_reader: EventReader<PhysicsBodiesEdited>,
+    settings: Res<ConservationSettings>,
+    simulation: Res<NBodySimulation>
+) {
+    let current: ConservedQuantities = simulation.state.conserved_quantities();
+    let body_count: usize = simulation.state.bodies.len();
+    let edited: bool = edited_reader.iter().count() > 0;
+    if edited || diagnostics.initial.is_none() || diagnostics.body_count != body_count {
+        diagnostics.initial = Some(current);
+        diagnostics.body_count = body_count;
+    }
+    let Some(initial) = diagnostics.initial else { return };
+
+    let previous_drifts: [f64; 3] = [
+        diagnostics.energy_drift,
+        diagnostics.linear_momentum_drift,
+        diagnostics.angular_momentum_drift
+    ];
+    diagnostics.current = current;
+    diagnostics.energy_drift = current.energy_drift(&initial);
+    diagnostics.linear_momentum_drift = current.linear_momentum_drift(&initial);
+    diagnostics.angular_momentum_drift = current.angular_momentum_drift(&initial);
+
+    let quantities: [ConservedQuantity; 3] = [
+        ConservedQuantity::Energy,
+        ConservedQuantity::LinearMomentum,
+        ConservedQuantity::AngularMomentum
+    ];
+    for (quantity, previous_drift) in quantities.into_iter().zip(previous_drifts) {
+        let relative_drift: f64 = diagnostics.drift(quantity);
+        if relative_drift > settings.drift_warning_threshold
+            && previous_drift <= settings.drift_warning_threshold {
+            warning_writer.send(ConservationDriftWarning { quantity, relative_drift });
+        }
+    }
+}
+
+fn log_conservation_diagnostics(
+    mut settings: ResMut<ConservationSettings>,
+    diagnostics: Res<ConservationDiagnostics>,
+    clock: Res<SimulationClock>,
+    time: Res<Time>
+) {
+    settings.log_timer.tick(time.delta());
+    if settings.log_timer.just_finished() {
+        println!(
+            "[{}] Energy: {:.6e} J (drift {:.3e}), Momentum drift: {:.3e}, Angular Momentum drift: {:.3e}",
+            clock.utc(),
+            diagnostics.current.total_energy_j(),
+            diagnostics.energy_drift,
+            diagnostics.linear_momentum_drift,
+            diagnostics.angular_momentum_drift
+        );
+    }
+}
+
+fn log_conservation_warnings(
+    mut warning_reader: EventReader<ConservationDriftWarning>,
+    settings: Res<ConservationSettings>
+) {
+    for warning in warning_reader.iter() {
+        println!(
+            "WARNING: {:?} drift {:.3e} has passed the threshold of {:.3e}",
+            warning.quantity, warning.relative_drift, settings.drift_warning_threshold
+        );
+    }
+}
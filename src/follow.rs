@@ -0,0 +1,108 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use crate::{
+    display_radius, BodyRegistry, CameraSetting, CurrentPlanetForCamera, DisplayScale, MainCamera,
+    ManualCameraController, SolarSystemObjectData
+};
+
+pub const LOOK_FROM_CLEARANCE: f32 = 1.5; // of the viewing body's radius, so it does not fill the view
+
+// KEEPS THE MANUAL CAMERA MOVING WITH A BODY
+// F follows the focused body, so WASD and the mouse move the camera relative to it.
+// V, while following, looks at the focused body from the followed one and tracks both
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct CameraFollow {
+    pub body: Option<Entity>,
+    pub look_at: Option<Entity>,
+    // where the followed body was drawn last frame
+    pub last_position: Option<Vec3>
+}
+
+impl CameraFollow {
+    pub fn is_following(&self) -> bool {
+        self.body.is_some()
+    }
+
+    pub fn stop(&mut self) {
+        self.body = None;
+        self.look_at = None;
+        self.last_position = None;
+    }
+}
+
+pub struct CameraFollowPlugin;
+
+impl Plugin for CameraFollowPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<CameraFollow>()
+            .add_system(camera_follow_controls)
+            .add_system(follow_camera_body.after(camera_follow_controls));
+    }
+}
+
+fn camera_follow_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut camera_query: Query<&mut CameraFollow, With<MainCamera>>,
+    mut camera_setting: ResMut<CameraSetting>,
+    current_planet: Res<CurrentPlanetForCamera>,
+    registry: Res<BodyRegistry>
+) {
+    let Ok(mut follow) = camera_query.get_single_mut() else { return };
+    let focused: Option<Entity> = current_planet.body.and_then(|id| registry.entity(id));
+
+    if keyboard.just_pressed(KeyCode::F) {
+        if follow.is_following() {
+            follow.stop();
+        } else if let Some(body) = focused {
+            // the camera stays where it is and moves with the body from now on
+            follow.body = Some(body);
+            *camera_setting = CameraSetting::Manual;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::V) && follow.is_following() {
+        follow.look_at = match follow.look_at {
+            Some(_) => None,
+            None => focused.filter(|target| Some(*target) != follow.body)
+        };
+    }
+    // the automatic camera places itself, so following ends when it takes over
+    if *camera_setting == CameraSetting::Automatic && follow.is_following() {
+        follow.stop();
+    }
+}
+
+/// Move the camera by however far the followed body moved since last frame.
+/// When looking at another body, sit just outside the followed body on the
+/// side facing the target instead, and look straight at it.
+fn follow_camera_body(
+    mut camera_query: Query<(&mut Transform, &mut CameraFollow, &mut ManualCameraController), With<MainCamera>>,
+    body_query: Query<(&Transform, &SolarSystemObjectData), Without<MainCamera>>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Aabb, &GlobalTransform)>,
+    display_scale: Res<DisplayScale>
+) {
+    let Ok((mut camera, mut follow, mut controller)) = camera_query.get_single_mut() else { return };
+    let Some(followed) = follow.body else { return };
+    let Ok((body_transform, object_data)) = body_query.get(followed) else {
+        // the body has gone
+        follow.stop();
+        return;
+    };
+    let position: Vec3 = body_transform.translation;
+
+    if let Some((target_transform, _)) = follow.look_at.and_then(|target| body_query.get(target).ok()) {
+        let target: Vec3 = target_transform.translation;
+        let radius: f32 = display_scale.radius(object_data.radius_m)
+            .max(display_radius(followed, position, &children_query, &mesh_query));
+        let towards_target: Vec3 = (target - position).normalize_or_zero();
+        camera.translation = position + towards_target * radius * LOOK_FROM_CLEARANCE;
+        camera.look_at(target, Vec3::Y);
+        // letting go of the target leaves the mouse orbiting it
+        controller.focus_distance = camera.translation.distance(target);
+    } else if let Some(last_position) = follow.last_position {
+        camera.translation += position - last_position;
+    }
+    follow.last_position = Some(position);
+}
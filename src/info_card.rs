@@ -0,0 +1,141 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::nbody::{Body, METRES_PER_AU, SECONDS_PER_DAY};
+use crate::{
+    text_style, BodyCategory, BodyRegistry, CurrentPlanetForCamera, ParentRelativeOrbit, PhysicsClock,
+    PreviousPhysicsPosition, SolarSystemObjectData, UiFont, SECONDS_PER_YEAR, UI_PANEL_COLOR
+};
+
+pub const INFO_CARD_WIDTH: f32 = 300.0;
+pub const INFO_CARD_TITLE_SIZE: f32 = 24.0;
+
+// the panel holding the card, hidden while nothing is focused
+#[derive(Component)]
+pub struct InfoCard;
+
+#[derive(Component)]
+pub struct InfoCardText;
+
+pub struct InfoCardPlugin;
+
+impl Plugin for InfoCardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(create_info_card)
+            .add_system(update_info_card);
+    }
+}
+
+/// A duration in whichever of hours, days or years reads best
+pub fn format_duration(seconds: f64) -> String {
+    let seconds: f64 = seconds.abs();
+    if seconds >= 2.0 * SECONDS_PER_YEAR {
+        format!("{:.2} years", seconds / SECONDS_PER_YEAR)
+    } else if seconds >= 2.0 * SECONDS_PER_DAY {
+        format!("{:.2} days", seconds / SECONDS_PER_DAY)
+    } else {
+        format!("{:.2} h", seconds / 3600.0)
+    }
+}
+
+// card in the bottom left corner, clear of the control panel
+fn create_info_card(mut commands: Commands, font: Res<UiFont>) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { left: Val::Px(10.0), bottom: Val::Px(10.0), ..default() },
+                size: Size::new(Val::Px(INFO_CARD_WIDTH), Val::Auto),
+                padding: UiRect::all(Val::Px(8.0)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: UI_PANEL_COLOR.into(),
+            ..default()
+        },
+        InfoCard
+    )).insert(Name::new("Info Card")).with_children(|card| {
+        card.spawn((
+            TextBundle::from_sections([
+                TextSection::new("", TextStyle { font_size: INFO_CARD_TITLE_SIZE, ..text_style(&font) }),
+                TextSection::new("", text_style(&font))
+            ]),
+            InfoCardText
+        ));
+    });
+}
+
+/// Fill the card from the focused body's live data, every frame
+fn update_info_card(
+    mut card_query: Query<&mut Style, With<InfoCard>>,
+    mut text_query: Query<&mut Text, With<InfoCardText>>,
+    object_query: Query<(&SolarSystemObjectData, Option<&ParentRelativeOrbit>)>,
+    previous_query: Query<&PreviousPhysicsPosition>,
+    current_planet: Res<CurrentPlanetForCamera>,
+    registry: Res<BodyRegistry>,
+    physics_clock: Res<PhysicsClock>
+) {
+    let focused = current_planet.body
+        .and_then(|id| registry.get(id))
+        .and_then(|body| object_query.get(body.entity).ok().map(|data| (body, data)));
+
+    for mut style in &mut card_query {
+        let display: Display = if focused.is_some() { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    let Some((body, (object_data, relative_orbit))) = focused else { return };
+
+    // between physics steps, as the bodies are drawn
+    let interpolated = |entity: Entity, object_data: &SolarSystemObjectData| -> Body {
+        let current: Body = object_data.to_body();
+        match previous_query.get(entity) {
+            Ok(previous) => previous.interpolate(&current, physics_clock.interpolation_alpha()),
+            Err(_) => current
+        }
+    };
+    let sun: Option<Body> = registry.iter()
+        .find(|body| body.category == BodyCategory::Star)
+        .and_then(|sun| object_query.get(sun.entity).ok().map(|(sun_data, _)| interpolated(sun.entity, sun_data)));
+    let body_state: Body = interpolated(body.entity, object_data);
+    let (from_sun, velocity_from_sun): (DVec3, DVec3) = match sun {
+        Some(sun_state) => body_state.relative_to(&sun_state),
+        None => (body_state.position, body_state.velocity)
+    };
+
+    let parent_name: &str = registry.parent_of(body.id).map_or("", |parent| parent.name.as_str());
+    let (orbital_period, eccentricity): (String, String) = match relative_orbit {
+        Some(orbit) => (
+            orbit.period_seconds()
+                .map_or("escaping".to_string(), |period| format!("{} around {}", format_duration(period), parent_name)),
+            format!("{:.4}", orbit.elements.eccentricity)
+        ),
+        None => ("-".to_string(), "-".to_string())
+    };
+    // spin is in rotations per Earth day, negative for retrograde rotation
+    let rotation_period: String = if object_data.spin == 0.0 {
+        "-".to_string()
+    } else {
+        format!(
+            "{}{}",
+            format_duration(SECONDS_PER_DAY / object_data.spin),
+            if object_data.spin < 0.0 { " (retrograde)" } else { "" }
+        )
+    };
+
+    let lines: String = format!(
+        "\nMass: {:.4e} kg\nDistance from Sun: {:.4} AU\nSpeed: {:.3} km/s\nOrbital period: {}\nEccentricity: {}\nAxial tilt: {:.2} deg\nRotation period: {}",
+        object_data.mass_kg,
+        from_sun.length() / METRES_PER_AU,
+        velocity_from_sun.length() / 1000.0,
+        orbital_period,
+        eccentricity,
+        object_data.tilt,
+        rotation_period
+    );
+    for mut text in &mut text_query {
+        text.sections[0].value.clone_from(&body.name);
+        text.sections[1].value = lines.clone();
+    }
+}
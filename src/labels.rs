@@ -0,0 +1,255 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy::ui::UiSystem;
+
+use crate::{text_style, BodyCategory, DisplayScale, MainCamera, MapCamera, MapView, SolarSystemObjectData, UiFont};
+
+pub const LABEL_FONT_SIZE: f32 = 14.0;
+pub const LABEL_OFFSET: f32 = 6.0; // pixels between a body and its label
+pub const LABEL_EDGE_MARGIN: f32 = 4.0;
+pub const LABEL_PLACEMENT_TRIES: i32 = 3; // nudges up and down before a label is dropped
+// metres from the camera, so labels fade at the same real distance whatever the display scale
+pub const MAJOR_LABEL_FADE_START: f64 = 1.3e12;  // about 9 AU
+pub const MAJOR_LABEL_FADE_END: f64 = 1.3e13;
+pub const MAJOR_LABEL_MIN_ALPHA: f32 = 0.4;
+pub const MINOR_LABEL_FADE_START: f64 = 1.3e10;  // about 0.09 AU
+pub const MINOR_LABEL_FADE_END: f64 = 3.9e10;
+
+// arrows for off-screen bodies, counter-clockwise from pointing right
+const EDGE_ARROWS: [&str; 8] = ["\u{2192}", "\u{2197}", "\u{2191}", "\u{2196}", "\u{2190}", "\u{2199}", "\u{2193}", "\u{2198}"];
+
+// SETTINGS FOR THE NAME LABELS, L TOGGLES THEM
+#[derive(Reflect, Resource)]
+pub struct BodyLabelSettings {
+    pub enabled: bool,
+    pub edge_indicators: bool
+}
+
+impl Default for BodyLabelSettings {
+    fn default() -> BodyLabelSettings {
+        BodyLabelSettings { enabled: true, edge_indicators: true }
+    }
+}
+
+// a UI text node naming one body
+#[derive(Component)]
+pub struct BodyLabel {
+    pub body: Entity,
+    pub name: String
+}
+
+// marks a body that already has a label
+#[derive(Component)]
+pub struct BodyLabelled;
+
+pub struct BodyLabelPlugin;
+
+impl Plugin for BodyLabelPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BodyLabelSettings>()
+            .init_resource::<BodyLabelSettings>()
+            .add_system(spawn_body_labels)
+            .add_system(toggle_body_labels)
+            // layout needs this frame's positions, so place labels just before it runs
+            .add_system_to_stage(CoreStage::PostUpdate, place_body_labels.before(UiSystem::Flex));
+    }
+}
+
+/// Labels drawn first win when two overlap: stars, then planets and so on
+fn label_priority(category: BodyCategory) -> u8 {
+    match category {
+        BodyCategory::Star => 0,
+        BodyCategory::Planet => 1,
+        BodyCategory::DwarfPlanet => 2,
+        BodyCategory::Moon => 3,
+        BodyCategory::Asteroid => 4,
+        BodyCategory::Spacecraft => 5
+    }
+}
+
+/// Opacity of a label for a body `distance_m` metres from the camera.
+/// Major bodies stay faintly labelled however far away they are, smaller ones
+/// fade out before their labels pile up on top of their parent's.
+pub fn label_alpha(category: BodyCategory, distance_m: f64) -> f32 {
+    let (start, end, min_alpha) = match category {
+        BodyCategory::Star | BodyCategory::Planet | BodyCategory::DwarfPlanet =>
+            (MAJOR_LABEL_FADE_START, MAJOR_LABEL_FADE_END, MAJOR_LABEL_MIN_ALPHA),
+        _ => (MINOR_LABEL_FADE_START, MINOR_LABEL_FADE_END, 0.0)
+    };
+    let fade: f32 = ((distance_m - start) / (end - start)).clamp(0.0, 1.0) as f32;
+    1.0 - fade * (1.0 - min_alpha)
+}
+
+fn spawn_body_labels(
+    mut commands: Commands,
+    font: Res<UiFont>,
+    body_query: Query<(Entity, &SolarSystemObjectData), Without<BodyLabelled>>,
+    label_query: Query<(Entity, &BodyLabel)>,
+    labelled_query: Query<(), With<BodyLabelled>>
+) {
+    for (entity, object_data) in &body_query {
+        commands.spawn((
+            TextBundle::from_section(
+                object_data.name.clone(),
+                TextStyle { font_size: LABEL_FONT_SIZE, ..text_style(&font) }
+            ).with_style(Style {
+                position_type: PositionType::Absolute,
+                display: Display::None,
+                ..default()
+            }),
+            // lets a label be clicked to select its body
+            Interaction::default(),
+            BodyLabel { body: entity, name: object_data.name.clone() }
+        )).insert(Name::new(format!("{} Label", object_data.name)));
+        commands.entity(entity).insert(BodyLabelled);
+    }
+    for (label_entity, label) in &label_query {
+        if labelled_query.get(label.body).is_err() {
+            commands.entity(label_entity).despawn_recursive();
+        }
+    }
+}
+
+fn toggle_body_labels(
+    keyboard: Res<Input<KeyCode>>,
+    mut settings: ResMut<BodyLabelSettings>
+) {
+    if keyboard.just_pressed(KeyCode::L) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+// where one label wants to go this frame
+struct LabelPlacement {
+    label: Entity,
+    priority: u8,
+    distance: f32,
+    anchor: Vec2,  // bottom left origin, like Camera::world_to_viewport
+    arrow: Option<&'static str>,
+    alpha: f32
+}
+
+// the cameras that can fill the window
+type WindowCameraFilter = Or<(With<MainCamera>, With<MapCamera>)>;
+
+/// Project every body through the camera filling the window and lay its
+/// label out on screen. Bodies off screen or behind the camera get an arrow on
+/// the nearest edge instead. Overlapping labels are nudged up or down, and dropped if no
+/// free spot is close by.
+fn place_body_labels(
+    mut label_query: Query<(Entity, &BodyLabel, &mut Style, &mut Text, &Node)>,
+    body_query: Query<(&Transform, Option<&BodyCategory>), With<SolarSystemObjectData>>,
+    camera_query: Query<(&Camera, &Transform, Option<&MapCamera>), WindowCameraFilter>,
+    settings: Res<BodyLabelSettings>,
+    map_view: Res<MapView>,
+    display_scale: Res<DisplayScale>
+) {
+    // labels go with whichever view fills the window, not the inset
+    let active_camera = camera_query.iter()
+        .find(|(camera, _, map_camera)| camera.is_active && map_camera.is_some() == map_view.enabled)
+        .map(|(camera, transform, _)| (camera, transform));
+    let (Some((camera, camera_transform)), true) = (active_camera, settings.enabled) else {
+        for (_, _, mut style, _, _) in &mut label_query {
+            if style.display != Display::None {
+                style.display = Display::None;
+            }
+        }
+        return;
+    };
+    let Some(viewport) = camera.logical_viewport_size() else { return };
+    // cameras have no parent, so their transform is already global
+    let camera_global: GlobalTransform = GlobalTransform::from(*camera_transform);
+    let world_to_view: Mat4 = camera_global.compute_matrix().inverse();
+    let centre: Vec2 = viewport / 2.0;
+
+    let mut placements: Vec<LabelPlacement> = Vec::new();
+    for (label_entity, label, _, _, _) in &label_query {
+        let Ok((transform, category)) = body_query.get(label.body) else { continue };
+        let category: BodyCategory = category.copied().unwrap_or_default();
+        let distance: f32 = transform.translation.distance(camera_transform.translation);
+        let alpha: f32 = label_alpha(category, display_scale.metres(distance));
+        if alpha <= 0.0 {
+            continue;
+        }
+
+        let on_screen: Option<Vec2> = camera.world_to_viewport(&camera_global, transform.translation)
+            .filter(|point| point.cmpge(Vec2::ZERO).all() && point.cmple(viewport).all());
+        let (anchor, arrow) = match on_screen {
+            Some(point) => (point + Vec2::splat(LABEL_OFFSET), None),
+            None if settings.edge_indicators => {
+                // on screen, a point's offset from the centre runs along its view space x and y
+                let view_position: Vec3 = world_to_view.transform_point3(transform.translation);
+                let direction: Vec2 = Vec2::new(view_position.x, view_position.y)
+                    .try_normalize()
+                    .unwrap_or(Vec2::NEG_Y);
+                let half_extent: Vec2 = centre - Vec2::splat(LABEL_EDGE_MARGIN);
+                let reach: f32 = (half_extent.x / direction.x.abs()).min(half_extent.y / direction.y.abs());
+                let octant: usize = ((direction.y.atan2(direction.x) / (PI / 4.0)).round() as i32)
+                    .rem_euclid(8) as usize;
+                (centre + direction * reach, Some(EDGE_ARROWS[octant]))
+            }
+            None => continue
+        };
+        placements.push(LabelPlacement {
+            label: label_entity,
+            priority: label_priority(category),
+            distance,
+            anchor,
+            arrow,
+            alpha
+        });
+    }
+    placements.sort_by(|a, b| a.priority.cmp(&b.priority).then(a.distance.total_cmp(&b.distance)));
+
+    let mut placed: Vec<Rect> = Vec::new();
+    let mut shown: Vec<Entity> = Vec::new();
+    for placement in &placements {
+        let Ok((_, label, mut style, mut text, node)) = label_query.get_mut(placement.label) else { continue };
+
+        let value: String = match placement.arrow {
+            Some(arrow) => format!("{} {}", arrow, label.name),
+            None => label.name.clone()
+        };
+        // layout runs after this, so guess the size of a label until it has one
+        let size: Vec2 = if node.size() == Vec2::ZERO {
+            Vec2::new(value.chars().count() as f32 * LABEL_FONT_SIZE * 0.6, LABEL_FONT_SIZE * 1.2)
+        } else {
+            node.size()
+        };
+        let origin: Vec2 = match placement.arrow {
+            // edge labels are centred on the arrow's spot and kept inside the window
+            Some(_) => (placement.anchor - size / 2.0).clamp(Vec2::ZERO, (viewport - size).max(Vec2::ZERO)),
+            None => placement.anchor
+        };
+
+        let free_spot: Option<Rect> = (0..=LABEL_PLACEMENT_TRIES * 2)
+            .map(|attempt| {
+                // 0, +1, -1, +2, -2... label heights
+                let step: i32 = (attempt + 1) / 2 * if attempt % 2 == 0 { -1 } else { 1 };
+                let corner: Vec2 = origin + Vec2::new(0.0, step as f32 * size.y);
+                Rect::from_corners(corner, corner + size)
+            })
+            .find(|rect| placed.iter().all(|other| rect.intersect(*other).is_empty()));
+        let Some(rect) = free_spot else { continue };
+        placed.push(rect);
+        shown.push(placement.label);
+
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+        text.sections[0].style.color.set_a(placement.alpha);
+        style.display = Display::Flex;
+        style.position = UiRect {
+            left: Val::Px(rect.min.x),
+            bottom: Val::Px(rect.min.y),
+            ..default()
+        };
+    }
+
+    for (label_entity, _, mut style, _, _) in &mut label_query {
+        if !shown.contains(&label_entity) && style.display != Display::None {
+            style.display = Display::None;
+        }
+    }
+}
@@ -1,10 +1,49 @@
 mod camera;
+mod catalog;
+mod clock;
+mod diagnostics;
+mod follow;
+mod info_card;
+mod labels;
+mod map;
+mod nbody;
+mod orbits;
+mod origin;
+mod physics;
+mod picking;
+mod picture_in_picture;
 mod planet;
+mod predictions;
+mod registry;
+mod scale;
+mod time_control;
+mod tour;
+mod trails;
+mod ui;
 
 use std::env;
 
 pub use camera::*;
+pub use catalog::*;
+pub use clock::*;
+pub use diagnostics::*;
+pub use follow::*;
+pub use info_card::*;
+pub use labels::*;
+pub use map::*;
+pub use orbits::*;
+pub use origin::*;
+pub use physics::*;
+pub use picking::*;
+pub use picture_in_picture::*;
 pub use planet::*;
+pub use predictions::*;
+pub use registry::*;
+pub use scale::*;
+pub use time_control::*;
+pub use tour::*;
+pub use trails::*;
+pub use ui::*;
 
 use bevy::app::AppExit;
 use bevy::prelude::*;
@@ -12,6 +51,7 @@ use bevy_inspector_egui::quick::WorldInspectorPlugin;
 
 pub const WIDTH: f32 = 1400.0;
 pub const HEIGHT: f32 = 1000.0;
+pub const WINDOW_TITLE: &str = "3D Solar System";
 
 fn exit_game(
     keyboard_input: Res<Input<KeyCode>>,
@@ -31,7 +71,7 @@ fn main() {
             window: WindowDescriptor {
                 width: WIDTH,
                 height: HEIGHT,
-                title: "3D Solar System".to_string(),
+                title: WINDOW_TITLE.to_string(),
                 resizable: false,
                 ..default()
             },
@@ -39,7 +79,25 @@ fn main() {
         }))
         .add_plugin(WorldInspectorPlugin)
         .add_plugin(UserCameraPlugin)
+        .add_plugin(SimulationClockPlugin)
+        .add_plugin(TimeControlPlugin)
+        .add_plugin(BodyRegistryPlugin)
+        .add_plugin(PhysicsPlugin)
+        .add_plugin(DisplayScalePlugin)
+        .add_plugin(FloatingOriginPlugin)
         .add_plugin(SolarSystemObjectPlugin)
+        .add_plugin(OrbitPlugin)
+        .add_plugin(OrbitTrailPlugin)
+        .add_plugin(OrbitPredictionPlugin)
+        .add_plugin(ConservationDiagnosticsPlugin)
+        .add_plugin(UIPlugin)
+        .add_plugin(InfoCardPlugin)
+        .add_plugin(BodyLabelPlugin)
+        .add_plugin(BodyPickingPlugin)
+        .add_plugin(GuidedTourPlugin)
+        .add_plugin(CameraFollowPlugin)
+        .add_plugin(MapViewPlugin)
+        .add_plugin(PictureInPicturePlugin)
         .add_system(exit_game)
         .run();
 }
@@ -0,0 +1,433 @@
+use std::f64::consts::TAU;
+
+use bevy::input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel};
+use bevy::math::{DVec2, DVec3};
+use bevy::prelude::*;
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::render::camera::{Projection, ScalingMode, Viewport};
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::view::{NoFrustumCulling, RenderLayers};
+use bevy::window::{WindowResized, WindowScaleFactorChanged};
+
+use crate::nbody::{ecliptic_to_world, OrbitalElements, METRES_PER_AU};
+use crate::{
+    cursor_in_viewport, inset_viewport, text_style, trail_color, DisplayScale, InsetFrame, MainCamera, OrbitParent,
+    ParentRelativeOrbit, RenderOrigin, SolarSystemObjectData, UiFont, RADIUS_TO_TRANSLATION_RATIO,
+    SCROLL_PIXELS_PER_LINE, SCROLL_ZOOM_SPEED, UI_PANEL_COLOR
+};
+
+pub const MAP_RENDER_LAYER: u8 = 1; // drawn by the map camera only
+pub const MAP_CAMERA_HEIGHT: f32 = 4000.0; // above the ecliptic, in render units
+pub const MAP_DEFAULT_SCALE: f32 = 16.0; // render units per pixel, enough for Neptune's orbit
+pub const MAP_MIN_SCALE: f32 = 1.0e-4;
+pub const MAP_MAX_SCALE: f32 = 100.0;
+pub const MAP_ICON_RADIUS: f32 = 6.0; // pixels
+pub const MAP_ORBIT_SEGMENTS: usize = 128;
+pub const MAP_GRID_MIN_SPACING: f32 = 60.0; // pixels between grid lines
+pub const MAP_GRID_MAX_LINES: usize = 256; // in each direction, beyond which the grid is left out
+pub const MAP_GRID_COLOR: Color = Color::rgba(0.5, 0.6, 0.8, 0.25);
+pub const MAP_ORBIT_ALPHA: f32 = 0.6;
+
+// THE TOP-DOWN MAP VIEW, M TOGGLES IT
+// looks down the ecliptic normal with +X to the right, replacing the 3D view while open;
+// inset is set while the picture in picture shows the map in a corner instead
+#[derive(Reflect, Resource, Default)]
+pub struct MapView {
+    pub enabled: bool,
+    pub inset: bool
+}
+
+impl MapView {
+    pub fn is_shown(&self) -> bool {
+        self.enabled || self.inset
+    }
+}
+
+// labels so the picture in picture can move the map camera between its controls and drawing
+#[derive(SystemLabel)]
+pub enum MapSystem {
+    Controls,
+    Draw
+}
+
+#[derive(Component)]
+pub struct MapCamera;
+
+// a fixed size marker for one body, drawn on the map only
+#[derive(Component)]
+pub struct MapIcon {
+    pub body: Entity
+}
+
+// the osculating orbit of one body around its parent, drawn on the map only
+#[derive(Component)]
+pub struct MapOrbitLine {
+    pub body: Entity
+}
+
+// marks a body that already has its map icon and orbit line
+#[derive(Component)]
+pub struct OnMap;
+
+#[derive(Component)]
+pub struct MapGrid;
+
+// names the grid spacing, shown while the map is open
+#[derive(Component)]
+pub struct MapGridText;
+
+// MESH SHARED BY EVERY MAP ICON
+#[derive(Resource)]
+pub struct MapIconMesh(pub Handle<Mesh>);
+
+impl FromWorld for MapIconMesh {
+    fn from_world(world: &mut World) -> MapIconMesh {
+        MapIconMesh(world.resource_mut::<Assets<Mesh>>().add(Mesh::from(shape::UVSphere { radius: 1.0, ..default() })))
+    }
+}
+
+pub struct MapViewPlugin;
+
+impl Plugin for MapViewPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<MapView>()
+            .init_resource::<MapView>()
+            .init_resource::<MapIconMesh>()
+            .add_startup_system(add_map_camera)
+            .add_system(spawn_map_markers)
+            .add_system(toggle_map_view)
+            .add_system(map_camera_controls.label(MapSystem::Controls).after(toggle_map_view))
+            .add_system(draw_map_icons.label(MapSystem::Draw).after(MapSystem::Controls))
+            .add_system(draw_map_orbits.label(MapSystem::Draw).after(MapSystem::Controls))
+            .add_system(draw_map_grid.label(MapSystem::Draw).after(MapSystem::Controls));
+    }
+}
+
+/// The first 1, 2 or 5 times a power of ten that is at least `minimum`
+pub fn grid_spacing(minimum: f64) -> f64 {
+    let power: f64 = 10f64.powf(minimum.log10().floor());
+    [1.0, 2.0, 5.0, 10.0].iter()
+        .map(|step| step * power)
+        .find(|spacing| *spacing >= minimum)
+        .unwrap_or(10.0 * power)
+}
+
+fn add_map_camera(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    font: Res<UiFont>
+) {
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                is_active: false,
+                priority: 1,
+                ..default()
+            },
+            projection: Projection::Orthographic(OrthographicProjection {
+                scale: MAP_DEFAULT_SCALE,
+                scaling_mode: ScalingMode::WindowSize,
+                near: 0.0,
+                far: 2.0 * MAP_CAMERA_HEIGHT,
+                ..default()
+            }),
+            // ecliptic +Y is world -Z, so that is up on the map
+            transform: Transform::from_xyz(0.0, MAP_CAMERA_HEIGHT, 0.0)
+                .looking_at(Vec3::ZERO, Vec3::NEG_Z),
+            ..default()
+        },
+        RenderLayers::from_layers(&[0, MAP_RENDER_LAYER]),
+        UiCameraConfig::default(),
+        MapCamera
+    )).insert(Name::new("Map Camera"));
+
+    commands.spawn((
+        PbrBundle {
+            mesh: meshes.add(Mesh::new(PrimitiveTopology::LineList)),
+            material: materials.add(StandardMaterial {
+                base_color: MAP_GRID_COLOR,
+                unlit: true,
+                alpha_mode: AlphaMode::Blend,
+                ..default()
+            }),
+            ..default()
+        },
+        NoFrustumCulling,
+        RenderLayers::layer(MAP_RENDER_LAYER),
+        MapGrid
+    )).insert(Name::new("Map Grid"));
+
+    commands.spawn((
+        TextBundle::from_section("", text_style(&font)).with_style(Style {
+            position_type: PositionType::Absolute,
+            position: UiRect { left: Val::Px(10.0), top: Val::Px(10.0), ..default() },
+            padding: UiRect::all(Val::Px(4.0)),
+            display: Display::None,
+            ..default()
+        }),
+        BackgroundColor(UI_PANEL_COLOR),
+        MapGridText
+    )).insert(Name::new("Map Grid Text"));
+}
+
+#[allow(clippy::too_many_arguments)]
+fn spawn_map_markers(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    icon_mesh: Res<MapIconMesh>,
+    body_query: Query<(Entity, &SolarSystemObjectData), Without<OnMap>>,
+    icon_query: Query<(Entity, &MapIcon)>,
+    line_query: Query<(Entity, &MapOrbitLine)>,
+    on_map_query: Query<(), With<OnMap>>
+) {
+    for (entity, object_data) in &body_query {
+        let color: Color = trail_color(entity);
+        let mut orbit_color: Color = color;
+        orbit_color.set_a(MAP_ORBIT_ALPHA);
+        commands.spawn((
+            PbrBundle {
+                mesh: icon_mesh.0.clone(),
+                material: materials.add(StandardMaterial { base_color: color, unlit: true, ..default() }),
+                ..default()
+            },
+            RenderLayers::layer(MAP_RENDER_LAYER),
+            MapIcon { body: entity }
+        )).insert(Name::new(format!("{} Map Icon", object_data.name)));
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+                material: materials.add(StandardMaterial {
+                    base_color: orbit_color,
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                }),
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            NoFrustumCulling,
+            RenderLayers::layer(MAP_RENDER_LAYER),
+            MapOrbitLine { body: entity }
+        )).insert(Name::new(format!("{} Map Orbit", object_data.name)));
+        commands.entity(entity).insert(OnMap);
+    }
+    for (marker, icon) in &icon_query {
+        if on_map_query.get(icon.body).is_err() {
+            commands.entity(marker).despawn_recursive();
+        }
+    }
+    for (marker, line) in &line_query {
+        if on_map_query.get(line.body).is_err() {
+            commands.entity(marker).despawn_recursive();
+        }
+    }
+}
+
+/// Swap the 3D camera for the map camera and back, or draw the map over the
+/// 3D view in the inset's corner. Labels and picking follow the camera
+/// drawn full screen, so they work on the map too.
+#[allow(clippy::too_many_arguments)]
+fn toggle_map_view(
+    keyboard: Res<Input<KeyCode>>,
+    mut map_view: ResMut<MapView>,
+    mut main_camera_query: Query<&mut Camera, (With<MainCamera>, Without<MapCamera>)>,
+    mut map_camera_query: Query<(&mut Camera, &mut Camera3d, &mut UiCameraConfig), With<MapCamera>>,
+    mut text_query: Query<&mut Style, With<MapGridText>>,
+    mut resized: EventReader<WindowResized>,
+    mut rescaled: EventReader<WindowScaleFactorChanged>,
+    windows: Res<Windows>
+) {
+    if keyboard.just_pressed(KeyCode::M) {
+        map_view.enabled = !map_view.enabled;
+    }
+    // the inset's viewport is in physical pixels, so it is redone whenever the window changes
+    let window_changed: bool = resized.iter().count() + rescaled.iter().count() > 0;
+    if !map_view.is_changed() && !window_changed {
+        return;
+    }
+    for mut camera in &mut main_camera_query {
+        camera.is_active = !map_view.enabled;
+    }
+    let inset: Option<Viewport> = windows.get_primary().map(inset_viewport);
+    for (mut camera, mut camera_3d, mut ui_config) in &mut map_camera_query {
+        camera.is_active = map_view.is_shown();
+        // an inset is drawn over the 3D view, so it must not clear the rest of the window
+        camera.viewport = if map_view.enabled { None } else { inset.clone() };
+        camera_3d.clear_color = if map_view.enabled { ClearColorConfig::Default } else { ClearColorConfig::None };
+        ui_config.show_ui = map_view.enabled;
+    }
+    for mut style in &mut text_query {
+        style.display = if map_view.enabled { Display::Flex } else { Display::None };
+    }
+}
+
+/// Drag with the left or middle mouse button to pan the map, and scroll to
+/// zoom in or out around the cursor. As an inset the map stays centred on the
+/// inset's target, so it can only be zoomed, with the cursor over it.
+#[allow(clippy::too_many_arguments)]
+fn map_camera_controls(
+    mut mouse_motion: EventReader<MouseMotion>,
+    mut mouse_wheel: EventReader<MouseWheel>,
+    mut camera_query: Query<(&Camera, &mut Transform, &mut Projection), With<MapCamera>>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    interaction_query: Query<&Interaction, With<Button>>,
+    frame_query: Query<&Interaction, With<InsetFrame>>,
+    windows: Res<Windows>,
+    map_view: Res<MapView>
+) {
+    let drag: Vec2 = mouse_motion.iter().map(|motion| motion.delta).sum();
+    let scroll: f32 = mouse_wheel.iter()
+        .map(|wheel| match wheel.unit {
+            MouseScrollUnit::Line => wheel.y,
+            MouseScrollUnit::Pixel => wheel.y / SCROLL_PIXELS_PER_LINE
+        })
+        .sum();
+    // over the full screen map the inset shows something else
+    let over_ui: bool = interaction_query.iter().any(|interaction| *interaction != Interaction::None)
+        || (map_view.enabled && frame_query.iter().any(|interaction| *interaction != Interaction::None));
+    let Ok((camera, mut transform, mut projection)) = camera_query.get_single_mut() else { return };
+    let Projection::Orthographic(orthographic) = projection.as_mut() else { return };
+    let cursor: Option<Vec2> = windows.get_primary()
+        .and_then(|window| window.cursor_position().and_then(|cursor| cursor_in_viewport(camera, window, cursor)));
+    if !map_view.is_shown() || over_ui || (!map_view.enabled && cursor.is_none()) {
+        return;
+    }
+
+    // mouse motion is measured downwards, and screen up is world -Z
+    let dragging: bool = mouse_buttons.pressed(MouseButton::Left) || mouse_buttons.pressed(MouseButton::Middle);
+    if map_view.enabled && dragging && drag != Vec2::ZERO {
+        transform.translation += Vec3::new(-drag.x, 0.0, -drag.y) * orthographic.scale;
+    }
+    if scroll != 0.0 {
+        let old_scale: f32 = orthographic.scale;
+        orthographic.scale = (old_scale * (-scroll * SCROLL_ZOOM_SPEED).exp()).clamp(MAP_MIN_SCALE, MAP_MAX_SCALE);
+        // keep the point under the cursor where it is, cursor positions are measured upwards
+        if let (Some(cursor), Some(viewport)) = (cursor, camera.logical_viewport_size()) {
+            let offset: Vec2 = cursor - viewport / 2.0;
+            let under_cursor: Vec3 = Vec3::new(offset.x, 0.0, -offset.y);
+            transform.translation += under_cursor * (old_scale - orthographic.scale);
+        }
+    }
+}
+
+/// Keep each icon on its body at the same size on screen whatever the zoom
+fn draw_map_icons(
+    mut icon_query: Query<(&MapIcon, &mut Transform)>,
+    body_query: Query<&Transform, (With<SolarSystemObjectData>, Without<MapIcon>)>,
+    camera_query: Query<&Projection, With<MapCamera>>,
+    map_view: Res<MapView>
+) {
+    let Ok(Projection::Orthographic(orthographic)) = camera_query.get_single() else { return };
+    if !map_view.is_shown() {
+        return;
+    }
+    for (icon, mut transform) in &mut icon_query {
+        let Ok(body_transform) = body_query.get(icon.body) else { continue };
+        transform.translation = body_transform.translation;
+        transform.scale = Vec3::splat(MAP_ICON_RADIUS * orthographic.scale);
+    }
+}
+
+/// Draw every body's osculating orbit around its current parent position
+fn draw_map_orbits(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut line_query: Query<(&MapOrbitLine, &Handle<Mesh>, &mut Visibility)>,
+    orbit_query: Query<(&ParentRelativeOrbit, &OrbitParent)>,
+    body_query: Query<&Transform, With<SolarSystemObjectData>>,
+    map_view: Res<MapView>,
+    display_scale: Res<DisplayScale>
+) {
+    if !map_view.is_shown() {
+        return;
+    }
+    for (line, mesh_handle, mut visibility) in &mut line_query {
+        let orbit = orbit_query.get(line.body).ok()
+            .filter(|(relative_orbit, _)| relative_orbit.elements.eccentricity < 1.0)
+            .and_then(|(relative_orbit, parent)| body_query.get(parent.entity).ok().map(|parent| (relative_orbit, parent)));
+        let visible: bool = orbit.is_some();
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+        let (Some((relative_orbit, parent_transform)), Some(mesh)) = (orbit, meshes.get_mut(mesh_handle)) else { continue };
+
+        let positions: Vec<[f32; 3]> = (0..=MAP_ORBIT_SEGMENTS)
+            .map(|segment| {
+                let elements: OrbitalElements = OrbitalElements {
+                    mean_anomaly: TAU * segment as f64 / MAP_ORBIT_SEGMENTS as f64,
+                    ..relative_orbit.elements
+                };
+                let (position, _): (DVec3, DVec3) = elements.to_state_vectors(relative_orbit.gravitational_parameter);
+                let offset: Vec3 = display_scale.offset(ecliptic_to_world(position)).as_vec3();
+                (parent_transform.translation + offset).to_array()
+            })
+            .collect();
+        let count: usize = positions.len();
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    }
+}
+
+/// Cover the visible part of the ecliptic plane with lines a round number of
+/// AU apart, closer together as the map zooms in. With log distances an AU
+/// has no fixed size on the map, so the grid is left out.
+fn draw_map_grid(
+    mut meshes: ResMut<Assets<Mesh>>,
+    grid_query: Query<&Handle<Mesh>, With<MapGrid>>,
+    mut text_query: Query<&mut Text, With<MapGridText>>,
+    camera_query: Query<(&Camera, &Transform, &Projection), With<MapCamera>>,
+    map_view: Res<MapView>,
+    display_scale: Res<DisplayScale>,
+    render_origin: Res<RenderOrigin>
+) {
+    let Ok((camera, camera_transform, Projection::Orthographic(orthographic))) = camera_query.get_single() else { return };
+    let (true, Some(viewport)) = (map_view.is_shown(), camera.logical_viewport_size()) else { return };
+
+    let units_per_au: f64 = METRES_PER_AU / RADIUS_TO_TRANSLATION_RATIO;
+    let spacing_au: f64 = grid_spacing((MAP_GRID_MIN_SPACING * orthographic.scale) as f64 / units_per_au);
+    let spacing: f64 = spacing_au * units_per_au;
+    let half_extent: Vec2 = viewport / 2.0 * orthographic.scale;
+    let centre: Vec2 = Vec2::new(camera_transform.translation.x, camera_transform.translation.z);
+    // lines sit on whole multiples of the spacing from the barycentre, so they are
+    // counted in f64 from there, as the map can be panned far from the render origin
+    let absolute_centre: DVec2 = DVec2::new(render_origin.position.x, render_origin.position.z) + centre.as_dvec2();
+    let first_index: DVec2 = ((absolute_centre - half_extent.as_dvec2()) / spacing).ceil();
+    let last_index: DVec2 = ((absolute_centre + half_extent.as_dvec2()) / spacing).floor();
+    let line_count: DVec2 = (last_index - first_index + 1.0).max(DVec2::ZERO);
+    let shown: bool = display_scale.is_linear() && line_count.max_element() <= MAP_GRID_MAX_LINES as f64;
+
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    if shown {
+        for index in (first_index.x as i64)..=(last_index.x as i64) {
+            // offset from the camera centre in f64, before it drops to f32
+            let x: f32 = centre.x + (index as f64 * spacing - absolute_centre.x) as f32;
+            positions.push([x, 0.0, centre.y - half_extent.y]);
+            positions.push([x, 0.0, centre.y + half_extent.y]);
+        }
+        for index in (first_index.y as i64)..=(last_index.y as i64) {
+            let z: f32 = centre.y + (index as f64 * spacing - absolute_centre.y) as f32;
+            positions.push([centre.x - half_extent.x, 0.0, z]);
+            positions.push([centre.x + half_extent.x, 0.0, z]);
+        }
+    }
+    let count: usize = positions.len();
+
+    for mesh_handle in &grid_query {
+        let Some(mesh) = meshes.get_mut(mesh_handle) else { continue };
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+    }
+    for mut text in &mut text_query {
+        let value: String = match (display_scale.is_linear(), shown) {
+            (true, true) => format!("Grid: {} AU", spacing_au),
+            (true, false) => "Grid: off, too many lines".to_string(),
+            (false, _) => "Grid: off, log distances".to_string()
+        };
+        if text.sections[0].value != value {
+            text.sections[0].value = value;
+        }
+    }
+}
@@ -0,0 +1,77 @@
+use glam::DVec3;
+
+use super::{Body, GRAV_CONST};
+
+/// Quantities that an isolated n-body system should conserve exactly.
+/// How far they wander is a measure of integration error.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct ConservedQuantities {
+    pub kinetic_energy_j: f64,
+    pub potential_energy_j: f64,
+    pub linear_momentum: DVec3,   // kg m s-1
+    pub angular_momentum: DVec3,  // kg m2 s-1
+    // sums of the magnitudes of each body's momentum and angular momentum,
+    // used as the scale for relative drift since the totals can be near zero
+    pub linear_momentum_scale: f64,
+    pub angular_momentum_scale: f64
+}
+
+impl ConservedQuantities {
+    pub fn of(bodies: &[Body]) -> ConservedQuantities {
+        let mut quantities: ConservedQuantities = ConservedQuantities::default();
+        for (i, body) in bodies.iter().enumerate() {
+            let momentum: DVec3 = body.velocity * body.mass_kg;
+            let angular_momentum: DVec3 = body.position.cross(momentum);
+            quantities.kinetic_energy_j += 0.5 * body.mass_kg * body.velocity.length_squared();
+            quantities.linear_momentum += momentum;
+            quantities.angular_momentum += angular_momentum;
+            quantities.linear_momentum_scale += momentum.length();
+            quantities.angular_momentum_scale += angular_momentum.length();
+
+            for other in &bodies[(i + 1)..] {
+                let distance: f64 = body.position.distance(other.position);
+                if distance > 0.0 {
+                    quantities.potential_energy_j -=
+                        GRAV_CONST * body.mass_kg * other.mass_kg / distance;
+                }
+            }
+        }
+        quantities
+    }
+
+    pub fn total_energy_j(&self) -> f64 {
+        self.kinetic_energy_j + self.potential_energy_j
+    }
+
+    /// Relative change in total energy since `initial`
+    pub fn energy_drift(&self, initial: &ConservedQuantities) -> f64 {
+        relative_change(
+            self.total_energy_j() - initial.total_energy_j(),
+            initial.total_energy_j().abs()
+        )
+    }
+
+    /// Change in linear momentum since `initial`, relative to the initial momentum scale
+    pub fn linear_momentum_drift(&self, initial: &ConservedQuantities) -> f64 {
+        relative_change(
+            (self.linear_momentum - initial.linear_momentum).length(),
+            initial.linear_momentum_scale
+        )
+    }
+
+    /// Change in angular momentum since `initial`, relative to the initial angular momentum scale
+    pub fn angular_momentum_drift(&self, initial: &ConservedQuantities) -> f64 {
+        relative_change(
+            (self.angular_momentum - initial.angular_momentum).length(),
+            initial.angular_momentum_scale
+        )
+    }
+}
+
+fn relative_change(change: f64, scale: f64) -> f64 {
+    if scale > 0.0 {
+        change.abs() / scale
+    } else {
+        change.abs()
+    }
+}
@@ -0,0 +1,408 @@
+use std::fmt;
+
+use glam::DVec3;
+
+use super::{METRES_PER_AU, SECONDS_PER_DAY};
+
+const METRES_PER_KM: f64 = 1000.0;
+// obliquity of the ecliptic at J2000, 84381.448 arcseconds
+const J2000_OBLIQUITY_DEGREES: f64 = 23.439_291_111;
+
+/// One state vector from a Horizons vector table, converted to metres and
+/// metres per second in the ecliptic frame (+Z north), relative to the
+/// table's center body
+#[derive(Clone, Copy, Debug)]
+pub struct StateRecord {
+    pub julian_date: f64,
+    pub position: DVec3,
+    pub velocity: DVec3
+}
+
+/// The contents of a JPL Horizons "Vector Table" export, in either the
+/// plain text or the CSV layout
+#[derive(Clone, Debug)]
+pub struct HorizonsVectors {
+    pub target_name: String,
+    pub center_name: String,
+    pub records: Vec<StateRecord>
+}
+
+impl HorizonsVectors {
+    /// The record closest to `julian_date`, if one is within `tolerance_days`
+    pub fn record_at(&self, julian_date: f64, tolerance_days: f64) -> Option<&StateRecord> {
+        self.records
+            .iter()
+            .min_by(|a, b| {
+                (a.julian_date - julian_date).abs().total_cmp(&(b.julian_date - julian_date).abs())
+            })
+            .filter(|record| (record.julian_date - julian_date).abs() <= tolerance_days)
+    }
+
+    /// True when the vectors are measured from the solar system barycenter
+    /// rather than from a body
+    pub fn is_barycentric(&self) -> bool {
+        self.center_name.to_ascii_lowercase().contains("barycenter")
+    }
+}
+
+#[derive(Debug)]
+pub struct HorizonsError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for HorizonsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.line == 0 {
+            write!(f, "{}", self.message)
+        } else {
+            write!(f, "line {}: {}", self.line, self.message)
+        }
+    }
+}
+
+impl std::error::Error for HorizonsError {}
+
+fn error(line: usize, message: impl Into<String>) -> HorizonsError {
+    HorizonsError { line, message: message.into() }
+}
+
+// how to turn the table's numbers into metres, metres per second and the ecliptic frame
+struct TableUnits {
+    metres_per_distance: f64,
+    seconds_per_time: f64,
+    equatorial: bool
+}
+
+impl TableUnits {
+    fn state(&self, position: DVec3, velocity: DVec3) -> (DVec3, DVec3) {
+        let position: DVec3 = position * self.metres_per_distance;
+        let velocity: DVec3 = velocity * (self.metres_per_distance / self.seconds_per_time);
+        if self.equatorial {
+            (equatorial_to_ecliptic(position), equatorial_to_ecliptic(velocity))
+        } else {
+            (position, velocity)
+        }
+    }
+}
+
+/// Rotate a vector from the ICRF equatorial frame into the J2000 ecliptic frame
+pub fn equatorial_to_ecliptic(vector: DVec3) -> DVec3 {
+    let (sin_obliquity, cos_obliquity) = J2000_OBLIQUITY_DEGREES.to_radians().sin_cos();
+    DVec3::new(
+        vector.x,
+        cos_obliquity * vector.y + sin_obliquity * vector.z,
+        -sin_obliquity * vector.y + cos_obliquity * vector.z
+    )
+}
+
+pub fn parse_horizons_vectors(text: &str) -> Result<HorizonsVectors, HorizonsError> {
+    let lines: Vec<&str> = text.lines().collect();
+    let header_value = |label: &str| -> Option<&str> {
+        lines.iter()
+            .find(|line| line.trim_start().starts_with(label))
+            .and_then(|line| line.split_once(':'))
+            .map(|(_, value)| value.trim())
+    };
+
+    let target_name: String = header_value("Target body name")
+        .map(body_name)
+        .ok_or_else(|| error(0, "missing 'Target body name' in the header"))?;
+    let center_name: String = header_value("Center body name")
+        .map(body_name)
+        .ok_or_else(|| error(0, "missing 'Center body name' in the header"))?;
+
+    // Horizons defaults to km and seconds when the units line is left out
+    let output_units: String = header_value("Output units").unwrap_or("KM-S").to_ascii_uppercase();
+    let (distance_unit, time_unit) = output_units.split_once('-')
+        .ok_or_else(|| error(0, format!("unrecognised output units '{}'", output_units)))?;
+    let metres_per_distance: f64 = match distance_unit.trim() {
+        "KM" => METRES_PER_KM,
+        "AU" => METRES_PER_AU,
+        other => return Err(error(0, format!("unrecognised distance unit '{}'", other)))
+    };
+    let seconds_per_time: f64 = match time_unit.trim() {
+        "S" => 1.0,
+        "D" => SECONDS_PER_DAY,
+        other => return Err(error(0, format!("unrecognised time unit '{}'", other)))
+    };
+
+    // newer exports name the plane separately from the frame
+    let plane: String = header_value("Reference plane")
+        .or_else(|| header_value("Reference frame"))
+        .unwrap_or("")
+        .to_ascii_uppercase();
+    let units: TableUnits = TableUnits {
+        metres_per_distance,
+        seconds_per_time,
+        equatorial: !plane.contains("ECLIP")
+    };
+
+    let start: usize = lines.iter().position(|line| line.trim() == "$$SOE")
+        .ok_or_else(|| error(0, "missing $$SOE marker before the data"))?;
+    let end: usize = lines.iter().position(|line| line.trim() == "$$EOE")
+        .ok_or_else(|| error(0, "missing $$EOE marker after the data"))?;
+    if end < start {
+        return Err(error(end + 1, "$$EOE comes before $$SOE"));
+    }
+
+    let data: &[&str] = &lines[(start + 1)..end];
+    let records: Vec<StateRecord> = if data.first().is_some_and(|line| line.contains(',')) {
+        // the CSV column names sit a couple of lines above $$SOE
+        let columns: Vec<String> = lines[..start]
+            .iter()
+            .rev()
+            .find(|line| line.contains("JDTDB") && line.contains(','))
+            .map(|line| line.split(',').map(|name| name.trim().to_string()).collect())
+            .ok_or_else(|| error(start, "missing the CSV column header above $$SOE"))?;
+        parse_csv_records(data, start + 2, &columns, &units)?
+    } else {
+        parse_text_records(data, start + 2, &units)?
+    };
+
+    if records.is_empty() {
+        return Err(error(start + 1, "no state vectors between $$SOE and $$EOE"));
+    }
+    Ok(HorizonsVectors { target_name, center_name, records })
+}
+
+// "Earth (399)    {source: DE441}" -> "Earth"
+fn body_name(value: &str) -> String {
+    let end: usize = value.find(['(', '{']).unwrap_or(value.len());
+    value[..end].trim().to_string()
+}
+
+fn parse_number(text: &str, line: usize, what: &str) -> Result<f64, HorizonsError> {
+    text.trim().parse::<f64>()
+        .map_err(|_| error(line, format!("could not read {} from '{}'", what, text.trim())))
+}
+
+fn parse_csv_records(
+    data: &[&str],
+    first_line: usize,
+    columns: &[String],
+    units: &TableUnits
+) -> Result<Vec<StateRecord>, HorizonsError> {
+    let column = |name: &str| -> Result<usize, HorizonsError> {
+        columns.iter().position(|column| column == name)
+            .ok_or_else(|| error(first_line - 1, format!("missing the {} column", name)))
+    };
+    let indices: [usize; 7] = [
+        column("JDTDB")?, column("X")?, column("Y")?, column("Z")?,
+        column("VX")?, column("VY")?, column("VZ")?
+    ];
+
+    let mut records: Vec<StateRecord> = Vec::new();
+    for (offset, line) in data.iter().enumerate() {
+        let line_number: usize = first_line + offset;
+        let fields: Vec<&str> = line.split(',').collect();
+        let mut values: [f64; 7] = [0.0; 7];
+        for (value, (index, name)) in values.iter_mut()
+            .zip(indices.iter().zip(["JDTDB", "X", "Y", "Z", "VX", "VY", "VZ"])) {
+            let field: &str = fields.get(*index)
+                .ok_or_else(|| error(line_number, format!("missing the {} value", name)))?;
+            *value = parse_number(field, line_number, name)?;
+        }
+        let (position, velocity) = units.state(
+            DVec3::new(values[1], values[2], values[3]),
+            DVec3::new(values[4], values[5], values[6])
+        );
+        records.push(StateRecord { julian_date: values[0], position, velocity });
+    }
+    Ok(records)
+}
+
+// the text layout puts each record over several lines:
+// 2451545.000000000 = A.D. 2000-Jan-01 12:00:00.0000 TDB
+//  X =-2.521092863852298E+07 Y = 1.449279195712076E+08 Z =-6.164888475164771E+02
+//  VX=-2.983983333368269E+01 VY=-5.207633918704476E+00 VZ= 6.169062303484907E-05
+fn parse_text_records(
+    data: &[&str],
+    first_line: usize,
+    units: &TableUnits
+) -> Result<Vec<StateRecord>, HorizonsError> {
+    let mut records: Vec<StateRecord> = Vec::new();
+    let mut current: Option<(usize, f64, [Option<f64>; 6])> = None;
+
+    let mut finish = |current: Option<(usize, f64, [Option<f64>; 6])>| -> Result<(), HorizonsError> {
+        let Some((line_number, julian_date, values)) = current else { return Ok(()) };
+        let mut components: [f64; 6] = [0.0; 6];
+        for (component, (value, name)) in components.iter_mut()
+            .zip(values.iter().zip(["X", "Y", "Z", "VX", "VY", "VZ"])) {
+            *component = value.ok_or_else(|| error(line_number, format!("record is missing {}", name)))?;
+        }
+        let (position, velocity) = units.state(
+            DVec3::new(components[0], components[1], components[2]),
+            DVec3::new(components[3], components[4], components[5])
+        );
+        records.push(StateRecord { julian_date, position, velocity });
+        Ok(())
+    };
+
+    for (offset, line) in data.iter().enumerate() {
+        let line_number: usize = first_line + offset;
+        let trimmed: &str = line.trim();
+        if trimmed.is_empty() {
+            continue;
+        }
+        if trimmed.starts_with(|c: char| c.is_ascii_digit()) {
+            // a new record starts with its Julian date
+            finish(current.take())?;
+            let date: &str = trimmed.split_whitespace().next().unwrap_or(trimmed);
+            current = Some((line_number, parse_number(date, line_number, "the Julian date")?, [None; 6]));
+            continue;
+        }
+        let Some((_, _, values)) = current.as_mut() else {
+            return Err(error(line_number, "found values before a Julian date"));
+        };
+        // split on '=' so each value follows the key at the end of the previous piece
+        let pieces: Vec<&str> = trimmed.split('=').collect();
+        for pair in pieces.windows(2) {
+            let key: &str = pair[0].split_whitespace().last().unwrap_or("");
+            let value: &str = pair[1].split_whitespace().next().unwrap_or("");
+            let slot: Option<usize> = ["X", "Y", "Z", "VX", "VY", "VZ"].iter().position(|name| *name == key);
+            if let Some(slot) = slot {
+                values[slot] = Some(parse_number(value, line_number, key)?);
+            }
+        }
+    }
+    finish(current.take())?;
+    Ok(records)
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::*;
+
+    // Earth from the Sun at J2000, from a DE441 export
+    const EARTH_POSITION_KM: [f64; 3] = [-2.521092863852298E+07, 1.449279195712076E+08, -6.164888475164771E+02];
+    const EARTH_VELOCITY_KM_S: [f64; 3] = [-2.983983333368269E+01, -5.207633918704476E+00, 6.169062303484907E-05];
+
+    const TEXT_TABLE: &str = "\
+*******************************************************************************
+Ephemeris / WWW_USER Sat Jan  6 12:00:00 2024 Pasadena, USA      / Horizons
+*******************************************************************************
+Target body name: Earth (399)                     {source: DE441}
+Center body name: Sun (10)                        {source: DE441}
+Center-site name: BODY CENTER
+*******************************************************************************
+Start time      : A.D. 2000-Jan-01 12:00:00.0000 TDB
+Stop  time      : A.D. 2000-Jan-01 12:00:00.0000 TDB
+Step-size       : 1440 minutes
+*******************************************************************************
+Output units    : KM-S
+Calendar mode   : Mixed Julian/Gregorian
+Output type     : GEOMETRIC cartesian states
+Output format   : 2 (position and velocity)
+Reference frame : ICRF
+Reference plane : Ecliptic of J2000.0
+*******************************************************************************
+JDTDB
+   X     Y     Z
+   VX    VY    VZ
+*******************************************************************************
+$$SOE
+2451545.000000000 = A.D. 2000-Jan-01 12:00:00.0000 TDB 
+ X =-2.521092863852298E+07 Y = 1.449279195712076E+08 Z =-6.164888475164771E+02
+ VX=-2.983983333368269E+01 VY=-5.207633918704476E+00 VZ= 6.169062303484907E-05
+$$EOE
+*******************************************************************************
+";
+
+    const CSV_TABLE: &str = "\
+*******************************************************************************
+Target body name: Earth (399)                     {source: DE441}
+Center body name: Sun (10)                        {source: DE441}
+*******************************************************************************
+Output units    : KM-S
+Reference frame : Ecliptic of J2000.0
+*******************************************************************************
+            JDTDB,            Calendar Date (TDB),                      X,                      Y,                      Z,                     VX,                     VY,                     VZ,
+**************************************************************************************************************************************************************************
+$$SOE
+2451545.000000000, A.D. 2000-Jan-01 12:00:00.0000, -2.521092863852298E+07,  1.449279195712076E+08, -6.164888475164771E+02, -2.983983333368269E+01, -5.207633918704476E+00,  6.169062303484907E-05,
+$$EOE
+";
+
+    fn assert_close(actual: DVec3, expected: DVec3) {
+        assert!(actual.distance(expected) <= 1e-12 * expected.length(), "{actual} != {expected}");
+    }
+
+    fn earth_in_metres() -> (DVec3, DVec3) {
+        (DVec3::from(EARTH_POSITION_KM) * 1000.0, DVec3::from(EARTH_VELOCITY_KM_S) * 1000.0)
+    }
+
+    #[test]
+    fn text_table_converts_km_to_metres() {
+        let table: HorizonsVectors = parse_horizons_vectors(TEXT_TABLE).unwrap();
+        assert_eq!(table.target_name, "Earth");
+        assert_eq!(table.center_name, "Sun");
+        assert!(!table.is_barycentric());
+        assert_eq!(table.records.len(), 1);
+
+        let record: &StateRecord = table.record_at(2451545.0, 1e-6).unwrap();
+        let (position, velocity) = earth_in_metres();
+        assert_close(record.position, position);
+        assert_close(record.velocity, velocity);
+        assert!(table.record_at(2451545.5, 1e-6).is_none());
+    }
+
+    #[test]
+    fn csv_table_matches_the_text_layout() {
+        let table: HorizonsVectors = parse_horizons_vectors(CSV_TABLE).unwrap();
+        assert_eq!(table.target_name, "Earth");
+        assert_eq!(table.records.len(), 1);
+        let (position, velocity) = earth_in_metres();
+        assert_close(table.records[0].position, position);
+        assert_close(table.records[0].velocity, velocity);
+        assert_eq!(table.records[0].julian_date, 2451545.0);
+    }
+
+    #[test]
+    fn au_per_day_tables_are_converted() {
+        let text: String = CSV_TABLE.replace("Output units    : KM-S", "Output units    : AU-D");
+        let table: HorizonsVectors = parse_horizons_vectors(&text).unwrap();
+        let position: DVec3 = DVec3::from(EARTH_POSITION_KM) * METRES_PER_AU;
+        let velocity: DVec3 = DVec3::from(EARTH_VELOCITY_KM_S) * (METRES_PER_AU / SECONDS_PER_DAY);
+        assert_close(table.records[0].position, position);
+        assert_close(table.records[0].velocity, velocity);
+    }
+
+    #[test]
+    fn equatorial_tables_are_rotated_into_the_ecliptic() {
+        let text: String = CSV_TABLE.replace("Reference frame : Ecliptic of J2000.0", "Reference frame : ICRF");
+        let table: HorizonsVectors = parse_horizons_vectors(&text).unwrap();
+        let (position, velocity) = earth_in_metres();
+        assert_close(table.records[0].position, equatorial_to_ecliptic(position));
+        assert_close(table.records[0].velocity, equatorial_to_ecliptic(velocity));
+    }
+
+    #[test]
+    fn equatorial_rotation_tilts_by_the_obliquity() {
+        // the celestial pole sits 23.44 degrees from the ecliptic pole
+        let pole: DVec3 = equatorial_to_ecliptic(DVec3::Z);
+        assert!((pole.angle_between(DVec3::Z).to_degrees() - 23.439_291_111).abs() < 1e-9);
+        assert_eq!(equatorial_to_ecliptic(DVec3::X), DVec3::X);
+    }
+
+    #[test]
+    fn barycentric_center_is_recognised() {
+        let text: String = CSV_TABLE.replace("Sun (10)", "Solar System Barycenter (0)");
+        let table: HorizonsVectors = parse_horizons_vectors(&text).unwrap();
+        assert_eq!(table.center_name, "Solar System Barycenter");
+        assert!(table.is_barycentric());
+    }
+
+    #[test]
+    fn malformed_tables_report_what_is_wrong() {
+        let no_data: String = CSV_TABLE.replace("$$SOE", "");
+        assert!(parse_horizons_vectors(&no_data).unwrap_err().message.contains("$$SOE"));
+        let bad_units: String = CSV_TABLE.replace("KM-S", "MI-H");
+        assert!(parse_horizons_vectors(&bad_units).unwrap_err().message.contains("MI"));
+        let bad_value: String = TEXT_TABLE.replace("Y = 1.449279195712076E+08", "Y = banana");
+        let error: HorizonsError = parse_horizons_vectors(&bad_value).unwrap_err();
+        assert!(error.line > 0 && error.message.contains("banana"), "{error}");
+    }
+}
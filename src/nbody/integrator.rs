@@ -0,0 +1,253 @@
+use glam::DVec3;
+
+use super::{Body, GRAV_CONST};
+
+// YOSHIDA 4TH ORDER COEFFICIENTS
+// w1 = 1 / (2 - 2^(1/3)), w0 = 1 - 2 * w1
+const YOSHIDA_W1: f64 = 1.351_207_191_959_657_8;
+const YOSHIDA_W0: f64 = -1.702_414_383_919_315_3;
+
+/// A numerical scheme for advancing a set of gravitating bodies by `dt` seconds
+pub trait Integrator: Send + Sync {
+    fn step(&self, bodies: &mut [Body], dt: f64);
+}
+
+// SELECT WHICH INTEGRATOR MOVES THE SOLAR SYSTEM
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum IntegratorMethod {
+    SemiImplicitEuler,
+    VelocityVerlet,
+    Leapfrog,
+    RungeKutta4,
+    #[default]
+    Yoshida4
+}
+
+impl IntegratorMethod {
+    pub fn integrator(&self) -> &'static dyn Integrator {
+        match self {
+            IntegratorMethod::SemiImplicitEuler => &SemiImplicitEuler,
+            IntegratorMethod::VelocityVerlet => &VelocityVerlet,
+            IntegratorMethod::Leapfrog => &Leapfrog,
+            IntegratorMethod::RungeKutta4 => &RungeKutta4,
+            IntegratorMethod::Yoshida4 => &Yoshida4,
+        }
+    }
+}
+
+/// Sum the gravitational acceleration on every body at the given positions.
+/// `positions` is indexed the same way as `bodies`, which lets the multi-stage
+/// integrators evaluate trial positions without touching the real state.
+pub fn accelerations_at(bodies: &[Body], positions: &[DVec3]) -> Vec<DVec3> {
+    let mut accelerations: Vec<DVec3> = vec![DVec3::ZERO; bodies.len()];
+    for i in 0..bodies.len() {
+        for j in (i + 1)..bodies.len() {
+            let separation: DVec3 = positions[j] - positions[i];
+            let distance_squared: f64 = separation.length_squared();
+            if distance_squared == 0.0 {
+                // DONT LET TWO OBJECTS IN THE SAME PLACE PULL ON EACH OTHER
+                continue;
+            }
+            // https://physics.stackexchange.com/questions/17285/split-gravitational-force-into-x-y-and-z-componenets
+            let pull: DVec3 = separation * (GRAV_CONST / (distance_squared * distance_squared.sqrt()));
+            accelerations[i] += pull * bodies[j].mass_kg;
+            accelerations[j] -= pull * bodies[i].mass_kg;
+        }
+    }
+    accelerations
+}
+
+/// Refresh the cached acceleration of every body from its current position
+pub fn compute_accelerations(bodies: &mut [Body]) {
+    let positions: Vec<DVec3> = bodies.iter().map(|body| body.position).collect();
+    let accelerations: Vec<DVec3> = accelerations_at(bodies, &positions);
+    for (body, acceleration) in bodies.iter_mut().zip(accelerations) {
+        body.acceleration = acceleration;
+    }
+}
+
+// shared kick and drift stages for the symplectic integrators
+fn kick(bodies: &mut [Body], dt: f64) {
+    for body in bodies.iter_mut() {
+        body.velocity += body.acceleration * dt;
+    }
+}
+
+fn drift(bodies: &mut [Body], dt: f64) {
+    for body in bodies.iter_mut() {
+        body.position += body.velocity * dt;
+    }
+}
+
+/// The original scheme: update speed from the current acceleration, then
+/// position from the new speed. First order, cheap, and drifts quickly.
+pub struct SemiImplicitEuler;
+
+impl Integrator for SemiImplicitEuler {
+    fn step(&self, bodies: &mut [Body], dt: f64) {
+        kick(bodies, dt);
+        drift(bodies, dt);
+        compute_accelerations(bodies);
+    }
+}
+
+/// Second order and symplectic, with one force evaluation per step
+pub struct VelocityVerlet;
+
+impl Integrator for VelocityVerlet {
+    fn step(&self, bodies: &mut [Body], dt: f64) {
+        let old_accelerations: Vec<DVec3> = bodies.iter().map(|body| body.acceleration).collect();
+        for body in bodies.iter_mut() {
+            body.position += body.velocity * dt + body.acceleration * (0.5 * dt * dt);
+        }
+        compute_accelerations(bodies);
+        for (body, old_acceleration) in bodies.iter_mut().zip(old_accelerations) {
+            body.velocity += (old_acceleration + body.acceleration) * (0.5 * dt);
+        }
+    }
+}
+
+/// Kick-drift-kick leapfrog. Equivalent to velocity Verlet in exact
+/// arithmetic, but written as half-step kicks so it composes into
+/// higher order schemes such as Yoshida's.
+pub struct Leapfrog;
+
+impl Integrator for Leapfrog {
+    fn step(&self, bodies: &mut [Body], dt: f64) {
+        kick(bodies, 0.5 * dt);
+        drift(bodies, dt);
+        compute_accelerations(bodies);
+        kick(bodies, 0.5 * dt);
+    }
+}
+
+/// Classic 4th order Runge-Kutta. Very accurate over short spans but not
+/// symplectic, so energy slowly leaks over many orbits.
+pub struct RungeKutta4;
+
+impl Integrator for RungeKutta4 {
+    fn step(&self, bodies: &mut [Body], dt: f64) {
+        let positions: Vec<DVec3> = bodies.iter().map(|body| body.position).collect();
+        let velocities: Vec<DVec3> = bodies.iter().map(|body| body.velocity).collect();
+
+        // each stage is (d position / dt, d velocity / dt)
+        let k1_velocity: Vec<DVec3> = velocities.clone();
+        let k1_acceleration: Vec<DVec3> = bodies.iter().map(|body| body.acceleration).collect();
+
+        let k2_velocity: Vec<DVec3> = offset(&velocities, &k1_acceleration, 0.5 * dt);
+        let k2_acceleration: Vec<DVec3> =
+            accelerations_at(bodies, &offset(&positions, &k1_velocity, 0.5 * dt));
+
+        let k3_velocity: Vec<DVec3> = offset(&velocities, &k2_acceleration, 0.5 * dt);
+        let k3_acceleration: Vec<DVec3> =
+            accelerations_at(bodies, &offset(&positions, &k2_velocity, 0.5 * dt));
+
+        let k4_velocity: Vec<DVec3> = offset(&velocities, &k3_acceleration, dt);
+        let k4_acceleration: Vec<DVec3> =
+            accelerations_at(bodies, &offset(&positions, &k3_velocity, dt));
+
+        for (i, body) in bodies.iter_mut().enumerate() {
+            body.position += (k1_velocity[i] + 2.0 * k2_velocity[i] + 2.0 * k3_velocity[i]
+                + k4_velocity[i]) * (dt / 6.0);
+            body.velocity += (k1_acceleration[i] + 2.0 * k2_acceleration[i]
+                + 2.0 * k3_acceleration[i] + k4_acceleration[i]) * (dt / 6.0);
+        }
+        compute_accelerations(bodies);
+    }
+}
+
+// values + rates * dt, element by element
+fn offset(values: &[DVec3], rates: &[DVec3], dt: f64) -> Vec<DVec3> {
+    values.iter().zip(rates).map(|(value, rate)| *value + *rate * dt).collect()
+}
+
+/// Yoshida's 4th order symplectic scheme, built as three leapfrog steps
+/// of lengths w1, w0 and w1. Costs three force evaluations per step.
+pub struct Yoshida4;
+
+impl Integrator for Yoshida4 {
+    fn step(&self, bodies: &mut [Body], dt: f64) {
+        for weight in [YOSHIDA_W1, YOSHIDA_W0, YOSHIDA_W1] {
+            Leapfrog.step(bodies, weight * dt);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::DVec3;
+
+    use super::IntegratorMethod;
+    use crate::nbody::tests::{circular_orbit, separation};
+    use crate::nbody::{ConservedQuantities, SystemState};
+
+    const ORBITS: u32 = 100;
+    const STEPS_PER_ORBIT: u32 = 200;
+
+    // largest relative energy error during each orbit of the run
+    fn energy_drift_per_orbit(method: IntegratorMethod) -> Vec<f64> {
+        let (mut state, period) = circular_orbit(method);
+        let initial: ConservedQuantities = state.conserved_quantities();
+        (0..ORBITS)
+            .map(|_| {
+                let mut worst: f64 = 0.0;
+                for _ in 0..STEPS_PER_ORBIT {
+                    state.step(period / STEPS_PER_ORBIT as f64);
+                    worst = worst.max(state.conserved_quantities().energy_drift(&initial));
+                }
+                worst
+            })
+            .collect()
+    }
+
+    #[test]
+    fn symplectic_energy_error_stays_bounded() {
+        for (method, bound) in [
+            (IntegratorMethod::VelocityVerlet, 1e-6),
+            (IntegratorMethod::Leapfrog, 1e-6),
+            (IntegratorMethod::Yoshida4, 1e-9)
+        ] {
+            let drifts: Vec<f64> = energy_drift_per_orbit(method);
+            let worst: f64 = drifts.iter().copied().fold(0.0, f64::max);
+            assert!(worst < bound, "{method:?} energy drifted by {worst}");
+            // bounded rather than growing, so the last orbit is about as good as the first
+            let (early, late) = (drifts[0], drifts[drifts.len() - 1]);
+            assert!(late < 2.0 * early, "{method:?} drifted by {late} in the last orbit, against {early} in the first");
+        }
+    }
+
+    #[test]
+    fn runge_kutta_is_accurate_over_one_orbit() {
+        let (mut state, period) = circular_orbit(IntegratorMethod::RungeKutta4);
+        let start: DVec3 = separation(&state);
+        for _ in 0..STEPS_PER_ORBIT {
+            state.step(period / STEPS_PER_ORBIT as f64);
+        }
+        let miss: f64 = separation(&state).distance(start) / start.length();
+        assert!(miss < 1e-6, "missed the starting point by {miss} of the orbit radius");
+    }
+
+    #[test]
+    fn symplectic_methods_retrace_their_steps_backwards() {
+        for method in [IntegratorMethod::VelocityVerlet, IntegratorMethod::Leapfrog, IntegratorMethod::Yoshida4] {
+            let (mut state, period) = circular_orbit(method);
+            let start: SystemState = state.clone();
+            let dt: f64 = period / STEPS_PER_ORBIT as f64;
+            for _ in 0..10 * STEPS_PER_ORBIT {
+                state.step(dt);
+            }
+            for _ in 0..10 * STEPS_PER_ORBIT {
+                state.step(-dt);
+            }
+            // only rounding error is left, relative to the orbit's size and speed
+            let radius: f64 = separation(&start).length();
+            let speed: f64 = (start.bodies[1].velocity - start.bodies[0].velocity).length();
+            for (body, initial) in state.bodies.iter().zip(&start.bodies) {
+                let position_error: f64 = body.position.distance(initial.position) / radius;
+                let velocity_error: f64 = body.velocity.distance(initial.velocity) / speed;
+                assert!(position_error < 1e-10, "{method:?} came back {position_error} of the radius out");
+                assert!(velocity_error < 1e-10, "{method:?} came back {velocity_error} of the speed out");
+            }
+        }
+    }
+}
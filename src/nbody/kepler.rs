@@ -0,0 +1,299 @@
+use std::f64::consts::{PI, TAU};
+
+use glam::{DQuat, DVec3};
+
+// below this an orbit is treated as circular or as lying in the reference plane
+const ELEMENT_EPSILON: f64 = 1e-11;
+const KEPLER_TOLERANCE: f64 = 1e-14;
+const KEPLER_MAX_ITERATIONS: u32 = 100;
+
+/// Classical orbital elements of a body about a central mass. Angles are in
+/// radians and measured in the ecliptic frame, where +Z is the ecliptic
+/// normal. Hyperbolic orbits (`eccentricity` > 1) use a negative semi-major axis.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct OrbitalElements {
+    pub semi_major_axis_m: f64,
+    pub eccentricity: f64,
+    pub inclination: f64,
+    pub longitude_of_ascending_node: f64,
+    pub argument_of_periapsis: f64,
+    pub mean_anomaly: f64
+}
+
+impl OrbitalElements {
+    /// Position and velocity relative to the central body, in the ecliptic
+    /// frame. `mu` is the gravitational parameter G * (M + m) in m3 s-2.
+    pub fn to_state_vectors(self, mu: f64) -> (DVec3, DVec3) {
+        let e: f64 = self.eccentricity;
+        let a: f64 = self.semi_major_axis_m.abs();
+
+        // position and velocity in the perifocal frame, +X towards periapsis
+        let (position, velocity): (DVec3, DVec3) = if e < 1.0 {
+            let eccentric_anomaly: f64 = solve_kepler_elliptic(self.mean_anomaly, e);
+            let (sin_e, cos_e) = eccentric_anomaly.sin_cos();
+            let semi_minor_factor: f64 = (1.0 - e * e).sqrt();
+            let radius: f64 = a * (1.0 - e * cos_e);
+            let speed_factor: f64 = (mu * a).sqrt() / radius;
+            (
+                DVec3::new(a * (cos_e - e), a * semi_minor_factor * sin_e, 0.0),
+                DVec3::new(-speed_factor * sin_e, speed_factor * semi_minor_factor * cos_e, 0.0)
+            )
+        } else {
+            let hyperbolic_anomaly: f64 = solve_kepler_hyperbolic(self.mean_anomaly, e);
+            let (sinh_h, cosh_h) = (hyperbolic_anomaly.sinh(), hyperbolic_anomaly.cosh());
+            let semi_minor_factor: f64 = (e * e - 1.0).sqrt();
+            let radius: f64 = a * (e * cosh_h - 1.0);
+            let speed_factor: f64 = (mu * a).sqrt() / radius;
+            (
+                DVec3::new(a * (e - cosh_h), a * semi_minor_factor * sinh_h, 0.0),
+                DVec3::new(-speed_factor * sinh_h, speed_factor * semi_minor_factor * cosh_h, 0.0)
+            )
+        };
+
+        let rotation: DQuat = DQuat::from_rotation_z(self.longitude_of_ascending_node)
+            * DQuat::from_rotation_x(self.inclination)
+            * DQuat::from_rotation_z(self.argument_of_periapsis);
+        (rotation * position, rotation * velocity)
+    }
+
+    /// Osculating elements for a body at `position` moving at `velocity`
+    /// relative to the central body, both in the ecliptic frame
+    pub fn from_state_vectors(position: DVec3, velocity: DVec3, mu: f64) -> OrbitalElements {
+        let radius: f64 = position.length();
+        let angular_momentum: DVec3 = position.cross(velocity);
+        let h: f64 = angular_momentum.length();
+        let node_vector: DVec3 = DVec3::Z.cross(angular_momentum);
+        let eccentricity_vector: DVec3 = velocity.cross(angular_momentum) / mu - position / radius;
+        let e: f64 = eccentricity_vector.length();
+        let specific_energy: f64 = 0.5 * velocity.length_squared() - mu / radius;
+        let semi_major_axis_m: f64 = -mu / (2.0 * specific_energy);
+
+        let inclination: f64 = (angular_momentum.z / h).clamp(-1.0, 1.0).acos();
+        let orbit_normal: DVec3 = angular_momentum / h;
+        let equatorial: bool = node_vector.length() < ELEMENT_EPSILON * h;
+        let circular: bool = e < ELEMENT_EPSILON;
+
+        // node direction, or +X when the orbit lies in the reference plane
+        let node_direction: DVec3 = if equatorial { DVec3::X } else { node_vector.normalize() };
+        let longitude_of_ascending_node: f64 = if equatorial {
+            0.0
+        } else {
+            node_direction.y.atan2(node_direction.x)
+        };
+
+        // periapsis direction, or the node direction for a circular orbit
+        let periapsis_direction: DVec3 = if circular {
+            node_direction
+        } else {
+            eccentricity_vector / e
+        };
+        let argument_of_periapsis: f64 = signed_angle(node_direction, periapsis_direction, orbit_normal);
+        let true_anomaly: f64 = signed_angle(periapsis_direction, position, orbit_normal);
+
+        let mean_anomaly: f64 = if e < 1.0 {
+            let eccentric_anomaly: f64 = 2.0 * ((1.0 - e).sqrt() * (0.5 * true_anomaly).sin())
+                .atan2((1.0 + e).sqrt() * (0.5 * true_anomaly).cos());
+            eccentric_anomaly - e * eccentric_anomaly.sin()
+        } else {
+            let hyperbolic_anomaly: f64 =
+                2.0 * (((e - 1.0) / (e + 1.0)).sqrt() * (0.5 * true_anomaly).tan()).atanh();
+            e * hyperbolic_anomaly.sinh() - hyperbolic_anomaly
+        };
+
+        OrbitalElements {
+            semi_major_axis_m,
+            eccentricity: e,
+            inclination,
+            longitude_of_ascending_node: longitude_of_ascending_node.rem_euclid(TAU),
+            argument_of_periapsis: argument_of_periapsis.rem_euclid(TAU),
+            mean_anomaly: if e < 1.0 { mean_anomaly.rem_euclid(TAU) } else { mean_anomaly }
+        }
+    }
+
+    /// Mean motion in radians per second
+    pub fn mean_motion(&self, mu: f64) -> f64 {
+        (mu / self.semi_major_axis_m.abs().powi(3)).sqrt()
+    }
+
+    /// Time for one orbit in seconds, or `None` for an open orbit
+    pub fn period_seconds(&self, mu: f64) -> Option<f64> {
+        (self.eccentricity < 1.0 && self.semi_major_axis_m > 0.0)
+            .then(|| TAU / self.mean_motion(mu))
+    }
+
+    /// The same orbit `seconds` later, assuming nothing else pulls on it
+    pub fn propagated(&self, mu: f64, seconds: f64) -> OrbitalElements {
+        let mean_anomaly: f64 = self.mean_anomaly + self.mean_motion(mu) * seconds;
+        OrbitalElements {
+            mean_anomaly: if self.eccentricity < 1.0 { mean_anomaly.rem_euclid(TAU) } else { mean_anomaly },
+            ..*self
+        }
+    }
+}
+
+// angle from `from` to `to` about `axis`, in -PI to PI
+fn signed_angle(from: DVec3, to: DVec3, axis: DVec3) -> f64 {
+    from.cross(to).dot(axis).atan2(from.dot(to))
+}
+
+/// Solve M = E - e sin E for the eccentric anomaly E, with 0 <= e < 1.
+/// Newton's method is kept inside a shrinking bracket and falls back to
+/// bisection whenever a step would leave it, so it always converges.
+pub fn solve_kepler_elliptic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    // solve in -PI..PI, where the root is bracketed, then shift back
+    let turns: f64 = ((mean_anomaly + PI) / TAU).floor();
+    let m: f64 = mean_anomaly - turns * TAU;
+    let kepler = |e_anomaly: f64| e_anomaly - eccentricity * e_anomaly.sin() - m;
+
+    let (mut low, mut high): (f64, f64) = (-PI, PI);
+    let mut e_anomaly: f64 = if eccentricity < 0.8 { m } else { PI.copysign(m) };
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let value: f64 = kepler(e_anomaly);
+        if value.abs() < KEPLER_TOLERANCE {
+            break;
+        }
+        if value > 0.0 { high = e_anomaly } else { low = e_anomaly }
+        let newton: f64 = e_anomaly - value / (1.0 - eccentricity * e_anomaly.cos());
+        e_anomaly = if newton > low && newton < high { newton } else { 0.5 * (low + high) };
+    }
+    e_anomaly + turns * TAU
+}
+
+/// Solve M = e sinh H - H for the hyperbolic anomaly H, with e > 1
+pub fn solve_kepler_hyperbolic(mean_anomaly: f64, eccentricity: f64) -> f64 {
+    let kepler = |h_anomaly: f64| eccentricity * h_anomaly.sinh() - h_anomaly - mean_anomaly;
+
+    // the function only increases, so widen a bracket until it holds the root
+    let (mut low, mut high): (f64, f64) = (-1.0, 1.0);
+    while kepler(low) > 0.0 { low *= 2.0 }
+    while kepler(high) < 0.0 { high *= 2.0 }
+
+    let mut h_anomaly: f64 = (mean_anomaly / eccentricity).asinh().clamp(low, high);
+    for _ in 0..KEPLER_MAX_ITERATIONS {
+        let value: f64 = kepler(h_anomaly);
+        if value.abs() < KEPLER_TOLERANCE * mean_anomaly.abs().max(1.0) {
+            break;
+        }
+        if value > 0.0 { high = h_anomaly } else { low = h_anomaly }
+        let newton: f64 = h_anomaly - value / (eccentricity * h_anomaly.cosh() - 1.0);
+        h_anomaly = if newton > low && newton < high { newton } else { 0.5 * (low + high) };
+    }
+    h_anomaly
+}
+
+/// Convert a vector from the ecliptic frame (+Z north) into the simulation
+/// frame, where +Y is up. Both frames are right handed.
+pub fn ecliptic_to_world(vector: DVec3) -> DVec3 {
+    DVec3::new(vector.x, vector.z, -vector.y)
+}
+
+pub fn world_to_ecliptic(vector: DVec3) -> DVec3 {
+    DVec3::new(vector.x, -vector.z, vector.y)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::{PI, TAU};
+
+    use glam::DVec3;
+
+    use super::*;
+
+    const SUN_MU: f64 = 1.327_124_400_18e20; // m3 s-2
+    const AU_M: f64 = 1.495_978_707e11;
+
+    // difference between two angles, wrapped into -PI..PI
+    fn angle_between(a: f64, b: f64) -> f64 {
+        (a - b + PI).rem_euclid(TAU) - PI
+    }
+
+    fn assert_same_orbit(expected: &OrbitalElements, actual: &OrbitalElements) {
+        let a_error: f64 = (actual.semi_major_axis_m - expected.semi_major_axis_m).abs()
+            / expected.semi_major_axis_m.abs();
+        assert!(a_error < 1e-10, "semi-major axis off by {a_error}: {actual:?}");
+        assert!((actual.eccentricity - expected.eccentricity).abs() < 1e-10, "eccentricity: {actual:?}");
+        for (name, expected_angle, actual_angle) in [
+            ("inclination", expected.inclination, actual.inclination),
+            ("node", expected.longitude_of_ascending_node, actual.longitude_of_ascending_node),
+            ("periapsis", expected.argument_of_periapsis, actual.argument_of_periapsis),
+            ("mean anomaly", expected.mean_anomaly, actual.mean_anomaly)
+        ] {
+            let error: f64 = angle_between(actual_angle, expected_angle).abs();
+            assert!(error < 1e-9, "{name} off by {error} rad: {actual:?}");
+        }
+    }
+
+    #[test]
+    fn elliptic_elements_round_trip_through_state_vectors() {
+        for eccentricity in [0.0167, 0.2056, 0.6, 0.99] {
+            let elements: OrbitalElements = OrbitalElements {
+                semi_major_axis_m: 1.3 * AU_M,
+                eccentricity,
+                inclination: 0.3,
+                longitude_of_ascending_node: 1.2,
+                argument_of_periapsis: 4.0,
+                mean_anomaly: 2.5
+            };
+            let (position, velocity): (DVec3, DVec3) = elements.to_state_vectors(SUN_MU);
+            assert_same_orbit(&elements, &OrbitalElements::from_state_vectors(position, velocity, SUN_MU));
+        }
+    }
+
+    #[test]
+    fn hyperbolic_elements_round_trip_through_state_vectors() {
+        for (eccentricity, mean_anomaly) in [(1.2, 0.4), (1.8, -3.0), (5.0, 25.0)] {
+            let elements: OrbitalElements = OrbitalElements {
+                semi_major_axis_m: -0.5 * AU_M,
+                eccentricity,
+                inclination: 2.0,
+                longitude_of_ascending_node: 0.7,
+                argument_of_periapsis: 5.5,
+                mean_anomaly
+            };
+            let (position, velocity): (DVec3, DVec3) = elements.to_state_vectors(SUN_MU);
+            // an escaping body has more than the escape speed
+            assert!(0.5 * velocity.length_squared() > SUN_MU / position.length());
+            assert_same_orbit(&elements, &OrbitalElements::from_state_vectors(position, velocity, SUN_MU));
+        }
+    }
+
+    #[test]
+    fn elliptic_solver_converges_near_parabolic() {
+        for eccentricity in [0.9, 0.99, 0.999, 0.999_999] {
+            for step in -40..=40 {
+                let mean_anomaly: f64 = step as f64 * 0.05 * PI;
+                let e_anomaly: f64 = solve_kepler_elliptic(mean_anomaly, eccentricity);
+                let residual: f64 = e_anomaly - eccentricity * e_anomaly.sin() - mean_anomaly;
+                assert!(residual.abs() < 1e-12, "e = {eccentricity}, M = {mean_anomaly}: residual {residual}");
+            }
+        }
+    }
+
+    #[test]
+    fn hyperbolic_solver_converges() {
+        for eccentricity in [1.000_001, 1.01, 1.5, 10.0] {
+            for mean_anomaly in [-1.0e3, -20.0, -1.0, -1e-6, 0.0, 1e-6, 0.5, 7.0, 1.0e4] {
+                let h_anomaly: f64 = solve_kepler_hyperbolic(mean_anomaly, eccentricity);
+                let residual: f64 = eccentricity * h_anomaly.sinh() - h_anomaly - mean_anomaly;
+                assert!(
+                    residual.abs() < 1e-10 * mean_anomaly.abs().max(1.0),
+                    "e = {eccentricity}, M = {mean_anomaly}: residual {residual}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn propagating_one_period_returns_to_the_start() {
+        let elements: OrbitalElements = OrbitalElements {
+            semi_major_axis_m: AU_M,
+            eccentricity: 0.5,
+            mean_anomaly: 1.0,
+            ..OrbitalElements::default()
+        };
+        let period: f64 = elements.period_seconds(SUN_MU).unwrap();
+        assert!((period / 86400.0 - 365.25).abs() < 0.1, "period of {} days", period / 86400.0);
+        let later: OrbitalElements = elements.propagated(SUN_MU, period);
+        assert!(angle_between(later.mean_anomaly, elements.mean_anomaly).abs() < 1e-9);
+    }
+}
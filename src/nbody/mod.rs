@@ -0,0 +1,197 @@
+//! Headless n-body gravity simulation.
+//!
+//! Nothing in here knows about Bevy or the rendering scale: bodies live in
+//! SI units (metres, metres per second, kilograms) and only depend on `glam`,
+//! so the same code can be driven from the game, a command line tool or a
+//! unit test. The Bevy side syncs a `SystemState` into ECS components.
+
+mod conservation;
+mod horizons;
+mod integrator;
+mod kepler;
+mod time;
+
+pub use conservation::*;
+pub use horizons::*;
+pub use integrator::*;
+pub use kepler::*;
+pub use time::*;
+
+use glam::DVec3;
+
+pub const GRAV_CONST: f64 = 6.6743e-11;  // m3 kg-1 s-2
+pub const METRES_PER_AU: f64 = 1.495_978_707e11;
+
+/// A single gravitating body. `acceleration` is kept in step with
+/// `position` by `SystemState`, which the integrators rely on.
+#[derive(Clone, Debug, Default)]
+pub struct Body {
+    pub name: String,
+    pub mass_kg: f64,
+    pub position: DVec3,
+    pub velocity: DVec3,
+    pub acceleration: DVec3
+}
+
+impl Body {
+    pub fn new(name: impl Into<String>, mass_kg: f64, position: DVec3, velocity: DVec3) -> Body {
+        Body {
+            name: name.into(),
+            mass_kg,
+            position,
+            velocity,
+            acceleration: DVec3::ZERO
+        }
+    }
+
+    /// Position and velocity of this body as seen from `parent`
+    pub fn relative_to(&self, parent: &Body) -> (DVec3, DVec3) {
+        (self.position - parent.position, self.velocity - parent.velocity)
+    }
+}
+
+/// Shift every body so the centre of mass sits at the origin and is at rest.
+/// Without this a system set up around a stationary Sun slowly drifts away.
+pub fn move_to_barycentric_frame(bodies: &mut [Body]) {
+    let total_mass: f64 = bodies.iter().map(|body| body.mass_kg).sum();
+    if total_mass <= 0.0 {
+        return;
+    }
+    let centre_of_mass: DVec3 = bodies.iter()
+        .map(|body| body.position * body.mass_kg)
+        .sum::<DVec3>() / total_mass;
+    let centre_of_mass_velocity: DVec3 = bodies.iter()
+        .map(|body| body.velocity * body.mass_kg)
+        .sum::<DVec3>() / total_mass;
+    for body in bodies.iter_mut() {
+        body.position -= centre_of_mass;
+        body.velocity -= centre_of_mass_velocity;
+    }
+}
+
+/// Every body in the simulation, plus the integrator used to move them
+#[derive(Clone, Debug, Default)]
+pub struct SystemState {
+    pub bodies: Vec<Body>,
+    pub integrator: IntegratorMethod,
+    pub elapsed_seconds: f64
+}
+
+impl SystemState {
+    pub fn new(integrator: IntegratorMethod) -> SystemState {
+        SystemState {
+            bodies: Vec::new(),
+            integrator,
+            elapsed_seconds: 0.0
+        }
+    }
+
+    /// Add a body and return its index in `bodies`. Indices shift down when an
+    /// earlier body is removed, so the app reads an object's current index from
+    /// its `PhysicsBody`, which is repacked whenever bodies are removed.
+    pub fn add_body(&mut self, body: Body) -> usize {
+        self.bodies.push(body);
+        compute_accelerations(&mut self.bodies);
+        self.bodies.len() - 1
+    }
+
+    /// Advance every body by `dt` seconds. A negative `dt` runs time backwards.
+    pub fn step(&mut self, dt: f64) {
+        self.integrator.integrator().step(&mut self.bodies, dt);
+        self.elapsed_seconds += dt;
+    }
+
+    pub fn conserved_quantities(&self) -> ConservedQuantities {
+        ConservedQuantities::of(&self.bodies)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::TAU;
+
+    use glam::DVec3;
+
+    use super::*;
+
+    const SUN_MASS_KG: f64 = 1.989e30;
+    const EARTH_MASS_KG: f64 = 5.972e24;
+    const ORBIT_RADIUS_M: f64 = 1.496e11;
+
+    /// A planet on a circular orbit around a star, in the barycentric frame,
+    /// and the orbit's period in seconds
+    pub(super) fn circular_orbit(integrator: IntegratorMethod) -> (SystemState, f64) {
+        let mu: f64 = GRAV_CONST * (SUN_MASS_KG + EARTH_MASS_KG);
+        let speed: f64 = (mu / ORBIT_RADIUS_M).sqrt();
+        let mut bodies: Vec<Body> = vec![
+            Body::new("Star", SUN_MASS_KG, DVec3::ZERO, DVec3::ZERO),
+            Body::new("Planet", EARTH_MASS_KG, DVec3::new(ORBIT_RADIUS_M, 0.0, 0.0), DVec3::new(0.0, speed, 0.0))
+        ];
+        move_to_barycentric_frame(&mut bodies);
+        let mut state: SystemState = SystemState::new(integrator);
+        for body in bodies {
+            state.add_body(body);
+        }
+        (state, TAU * (ORBIT_RADIUS_M.powi(3) / mu).sqrt())
+    }
+
+    // separation of the planet from the star
+    pub(super) fn separation(state: &SystemState) -> DVec3 {
+        state.bodies[1].position - state.bodies[0].position
+    }
+
+    #[test]
+    fn circular_orbit_keeps_its_radius_and_period() {
+        let (mut state, period) = circular_orbit(IntegratorMethod::Yoshida4);
+        let start: DVec3 = separation(&state);
+        let steps: u32 = 1000;
+        for _ in 0..steps {
+            state.step(period / steps as f64);
+            let radius: f64 = separation(&state).length();
+            assert!((radius - ORBIT_RADIUS_M).abs() < 1e-6 * ORBIT_RADIUS_M, "radius drifted to {radius}");
+        }
+        // back where it started after one period
+        assert!((state.elapsed_seconds - period).abs() < 1e-6 * period);
+        let miss: f64 = separation(&state).distance(start);
+        assert!(miss < 1e-6 * ORBIT_RADIUS_M, "missed the starting point by {miss} m");
+    }
+
+    #[test]
+    fn half_a_period_reaches_the_far_side() {
+        let (mut state, period) = circular_orbit(IntegratorMethod::Yoshida4);
+        let start: DVec3 = separation(&state);
+        for _ in 0..500 {
+            state.step(period / 1000.0);
+        }
+        let opposite: f64 = separation(&state).distance(-start);
+        assert!(opposite < 1e-6 * ORBIT_RADIUS_M, "{opposite} m from the far side");
+    }
+
+    #[test]
+    fn barycentric_frame_has_no_total_momentum() {
+        let mut bodies: Vec<Body> = vec![
+            Body::new("A", 2.0e30, DVec3::new(1.0e9, -2.0e9, 3.0e8), DVec3::new(1.0e3, 2.0e4, -5.0e2)),
+            Body::new("B", 6.0e24, DVec3::new(1.5e11, 0.0, 0.0), DVec3::new(0.0, 3.0e4, 1.0e2)),
+            Body::new("C", 7.3e22, DVec3::new(1.5e11, 3.8e8, 0.0), DVec3::new(-1.0e3, 3.0e4, 0.0))
+        ];
+        move_to_barycentric_frame(&mut bodies);
+
+        let momentum: DVec3 = bodies.iter().map(|body| body.velocity * body.mass_kg).sum();
+        let momentum_scale: f64 = bodies.iter().map(|body| (body.velocity * body.mass_kg).length()).sum();
+        assert!(momentum.length() < 1e-9 * momentum_scale, "momentum left: {momentum}");
+
+        let total_mass: f64 = bodies.iter().map(|body| body.mass_kg).sum();
+        let centre_of_mass: DVec3 = bodies.iter().map(|body| body.position * body.mass_kg).sum::<DVec3>() / total_mass;
+        assert!(centre_of_mass.length() < 1.0, "centre of mass at {centre_of_mass} m");
+    }
+
+    #[test]
+    fn state_steps_without_a_game_engine() {
+        let (mut state, period) = circular_orbit(IntegratorMethod::VelocityVerlet);
+        let initial: ConservedQuantities = state.conserved_quantities();
+        state.step(period / 1000.0);
+        state.step(-period / 1000.0);
+        assert!(state.elapsed_seconds.abs() < 1e-9);
+        assert!(state.conserved_quantities().energy_drift(&initial) < 1e-9);
+    }
+}
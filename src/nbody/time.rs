@@ -0,0 +1,247 @@
+use std::fmt;
+
+pub const J2000_JULIAN_DATE: f64 = 2451545.0;
+pub const SECONDS_PER_DAY: f64 = 86400.0;
+const TT_MINUS_TAI_SECONDS: f64 = 32.184;
+
+// (year, month, TAI - UTC in seconds) from the first day of that month, as
+// published by the IERS. Dates before 1972 use the first entry.
+const LEAP_SECONDS: [(i64, u32, f64); 28] = [
+    (1972, 1, 10.0), (1972, 7, 11.0), (1973, 1, 12.0), (1974, 1, 13.0),
+    (1975, 1, 14.0), (1976, 1, 15.0), (1977, 1, 16.0), (1978, 1, 17.0),
+    (1979, 1, 18.0), (1980, 1, 19.0), (1981, 7, 20.0), (1982, 7, 21.0),
+    (1983, 7, 22.0), (1985, 7, 23.0), (1988, 1, 24.0), (1990, 1, 25.0),
+    (1991, 1, 26.0), (1992, 7, 27.0), (1993, 7, 28.0), (1994, 7, 29.0),
+    (1996, 1, 30.0), (1997, 7, 31.0), (1999, 1, 32.0), (2006, 1, 33.0),
+    (2009, 1, 34.0), (2012, 7, 35.0), (2015, 7, 36.0), (2017, 1, 37.0)
+];
+
+/// Barycentric dynamical time (TDB) in seconds since J2000, the time scale
+/// the simulation and Horizons ephemerides run on
+pub fn julian_date_to_tdb_seconds(julian_date: f64) -> f64 {
+    (julian_date - J2000_JULIAN_DATE) * SECONDS_PER_DAY
+}
+
+pub fn tdb_seconds_to_julian_date(tdb_seconds: f64) -> f64 {
+    J2000_JULIAN_DATE + tdb_seconds / SECONDS_PER_DAY
+}
+
+/// A proleptic Gregorian date and time of day in UTC
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CalendarDate {
+    pub year: i64,
+    pub month: u32,
+    pub day: u32,
+    pub hour: u32,
+    pub minute: u32,
+    pub second: f64
+}
+
+impl CalendarDate {
+    /// The UTC date at `tdb_seconds` since J2000. During an inserted leap
+    /// second the clock reads 23:59:60.
+    pub fn from_tdb_seconds(tdb_seconds: f64) -> CalendarDate {
+        let tt_seconds: f64 = tdb_seconds - tdb_minus_tt_seconds(tdb_seconds);
+        let tai_seconds: f64 = tt_seconds - TT_MINUS_TAI_SECONDS;
+        let offset: f64 = tai_minus_utc_at_tai_seconds(tai_seconds);
+        let utc_seconds: f64 = tai_seconds - offset;
+        if tai_minus_utc_seconds(utc_seconds) > offset {
+            // past midnight by the old offset but not yet by the new one
+            let mut date: CalendarDate = CalendarDate::from_utc_seconds(utc_seconds - 1.0);
+            date.second += 1.0;
+            date
+        } else {
+            CalendarDate::from_utc_seconds(utc_seconds)
+        }
+    }
+
+    /// This UTC date as TDB seconds since J2000
+    pub fn to_tdb_seconds(self) -> f64 {
+        let utc_seconds: f64 = self.utc_seconds();
+        // a leap second at 23:59:60 still has the offset of the day it ends
+        let offset_at: f64 = if self.second >= 60.0 { utc_seconds - self.second } else { utc_seconds };
+        let tt_seconds: f64 = utc_seconds + tai_minus_utc_seconds(offset_at) + TT_MINUS_TAI_SECONDS;
+        tt_seconds + tdb_minus_tt_seconds(tt_seconds)
+    }
+
+    // seconds since 2000-01-01 12:00:00 UTC, counting every day as 86400 seconds
+    fn utc_seconds(&self) -> f64 {
+        let days: i64 = days_from_civil(self.year, self.month, self.day) - days_from_civil(2000, 1, 1);
+        days as f64 * SECONDS_PER_DAY
+            + (self.hour as f64 - 12.0) * 3600.0
+            + self.minute as f64 * 60.0
+            + self.second
+    }
+
+    fn from_utc_seconds(utc_seconds: f64) -> CalendarDate {
+        let since_midnight: f64 = utc_seconds + 0.5 * SECONDS_PER_DAY;
+        let days: f64 = (since_midnight / SECONDS_PER_DAY).floor();
+        let seconds_of_day: f64 = since_midnight - days * SECONDS_PER_DAY;
+        let (year, month, day) = civil_from_days(days as i64 + days_from_civil(2000, 1, 1));
+        let hour: u32 = (seconds_of_day / 3600.0).floor().min(23.0) as u32;
+        let minute: u32 = ((seconds_of_day - hour as f64 * 3600.0) / 60.0).floor().min(59.0) as u32;
+        CalendarDate {
+            year,
+            month,
+            day,
+            hour,
+            minute,
+            second: seconds_of_day - hour as f64 * 3600.0 - minute as f64 * 60.0
+        }
+    }
+}
+
+impl fmt::Display for CalendarDate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} UTC",
+            self.year, self.month, self.day, self.hour, self.minute, self.second.floor() as u32
+        )
+    }
+}
+
+// TAI - UTC at a UTC time given in seconds since J2000
+fn tai_minus_utc_seconds(utc_seconds: f64) -> f64 {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(year, month, _)| utc_seconds >= leap_second_change(*year, *month))
+        .map_or(LEAP_SECONDS[0].2, |(_, _, offset)| *offset)
+}
+
+// TAI - UTC at a TAI time given in seconds since J2000, where each offset
+// starts once TAI reaches the new UTC day
+fn tai_minus_utc_at_tai_seconds(tai_seconds: f64) -> f64 {
+    LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(year, month, offset)| tai_seconds >= leap_second_change(*year, *month) + offset)
+        .map_or(LEAP_SECONDS[0].2, |(_, _, offset)| *offset)
+}
+
+// the UTC start of the month an offset takes effect, in seconds since J2000
+fn leap_second_change(year: i64, month: u32) -> f64 {
+    CalendarDate { year, month, day: 1, hour: 0, minute: 0, second: 0.0 }.utc_seconds()
+}
+
+// the largest periodic terms of TDB - TT, good to a few microseconds
+fn tdb_minus_tt_seconds(tt_seconds: f64) -> f64 {
+    let mean_anomaly: f64 = (357.53 + 0.98560028 * tt_seconds / SECONDS_PER_DAY).to_radians();
+    0.001657 * mean_anomaly.sin() + 0.000014 * (2.0 * mean_anomaly).sin()
+}
+
+// days since 1970-01-01 for a proleptic Gregorian date
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let year: i64 = if month <= 2 { year - 1 } else { year };
+    let era: i64 = year.div_euclid(400);
+    let year_of_era: i64 = year - era * 400;
+    let month_index: i64 = (month as i64 + 9) % 12;
+    let day_of_year: i64 = (153 * month_index + 2) / 5 + day as i64 - 1;
+    let day_of_era: i64 = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let days: i64 = days + 719468;
+    let era: i64 = days.div_euclid(146097);
+    let day_of_era: i64 = days - era * 146097;
+    let year_of_era: i64 =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year: i64 = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index: i64 = (5 * day_of_year + 2) / 153;
+    let day: u32 = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month: u32 = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year: i64 = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i64, month: u32, day: u32, hour: u32, minute: u32, second: f64) -> CalendarDate {
+        CalendarDate { year, month, day, hour, minute, second }
+    }
+
+    fn assert_same_date(actual: CalendarDate, expected: CalendarDate) {
+        let seconds_match: bool = (actual.second - expected.second).abs() < 1e-4;
+        assert!(CalendarDate { second: expected.second, ..actual } == expected && seconds_match, "{actual:?} != {expected:?}");
+    }
+
+    #[test]
+    fn j2000_is_the_epoch() {
+        assert_eq!(julian_date_to_tdb_seconds(J2000_JULIAN_DATE), 0.0);
+        assert_eq!(tdb_seconds_to_julian_date(0.0), J2000_JULIAN_DATE);
+        assert_eq!(tdb_seconds_to_julian_date(julian_date_to_tdb_seconds(2460000.25)), 2460000.25);
+        // J2000 is noon TT, 64.184 seconds ahead of UTC in 2000
+        assert_same_date(CalendarDate::from_tdb_seconds(0.0), date(2000, 1, 1, 11, 58, 55.816));
+    }
+
+    #[test]
+    fn terrestrial_time_runs_32_184_seconds_ahead_of_atomic_time() {
+        let utc: CalendarDate = date(2020, 6, 1, 0, 0, 0.0);
+        let tdb_seconds: f64 = utc.to_tdb_seconds();
+        let tt_seconds: f64 = tdb_seconds - tdb_minus_tt_seconds(tdb_seconds);
+        let tai_seconds: f64 = utc.utc_seconds() + 37.0;
+        assert!((tt_seconds - tai_seconds - 32.184).abs() < 1e-6, "TT - TAI = {}", tt_seconds - tai_seconds);
+    }
+
+    #[test]
+    fn tdb_stays_within_two_milliseconds_of_tt() {
+        for day in 0..366 {
+            let tt_seconds: f64 = day as f64 * SECONDS_PER_DAY;
+            assert!(tdb_minus_tt_seconds(tt_seconds).abs() < 0.002);
+        }
+    }
+
+    #[test]
+    fn leap_seconds_follow_the_iers_table() {
+        assert_eq!(tai_minus_utc_seconds(date(1999, 1, 1, 0, 0, 0.0).utc_seconds()), 32.0);
+        assert_eq!(tai_minus_utc_seconds(date(2016, 12, 31, 23, 59, 59.0).utc_seconds()), 36.0);
+        assert_eq!(tai_minus_utc_seconds(date(2017, 1, 1, 0, 0, 0.0).utc_seconds()), 37.0);
+        // before 1972 the first offset is used
+        assert_eq!(tai_minus_utc_seconds(date(1960, 1, 1, 0, 0, 0.0).utc_seconds()), 10.0);
+    }
+
+    #[test]
+    fn the_leap_second_at_the_end_of_2016_is_counted() {
+        let before: f64 = date(2016, 12, 31, 23, 59, 59.0).to_tdb_seconds();
+        let leap: f64 = date(2016, 12, 31, 23, 59, 60.0).to_tdb_seconds();
+        let after: f64 = date(2017, 1, 1, 0, 0, 0.0).to_tdb_seconds();
+        assert!((leap - before - 1.0).abs() < 1e-6, "23:59:60 is {} s after 23:59:59", leap - before);
+        assert!((after - leap - 1.0).abs() < 1e-6, "midnight is {} s after 23:59:60", after - leap);
+
+        assert_same_date(CalendarDate::from_tdb_seconds(before), date(2016, 12, 31, 23, 59, 59.0));
+        assert_same_date(CalendarDate::from_tdb_seconds(leap + 0.5), date(2016, 12, 31, 23, 59, 60.5));
+        assert_same_date(CalendarDate::from_tdb_seconds(after), date(2017, 1, 1, 0, 0, 0.0));
+    }
+
+    #[test]
+    fn dates_round_trip_through_tdb() {
+        for utc in [
+            date(1972, 7, 1, 0, 0, 0.0),
+            date(2000, 2, 29, 23, 59, 59.5),
+            date(2024, 3, 10, 6, 30, 12.25),
+            date(1850, 6, 15, 18, 0, 0.0),
+            date(2100, 12, 31, 12, 0, 0.0)
+        ] {
+            assert_same_date(CalendarDate::from_tdb_seconds(utc.to_tdb_seconds()), utc);
+        }
+    }
+
+    #[test]
+    fn civil_days_count_from_1970() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1) - days_from_civil(2000, 2, 28), 2);
+        for days in [-1_000_000, -1, 0, 10_957, 2_932_896] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+    }
+
+    #[test]
+    fn dates_print_in_utc() {
+        assert_eq!(date(2016, 12, 31, 23, 59, 60.5).to_string(), "2016-12-31 23:59:60 UTC");
+    }
+}
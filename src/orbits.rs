@@ -0,0 +1,80 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::nbody::{world_to_ecliptic, OrbitalElements, GRAV_CONST};
+use crate::{PhysicsStage, PhysicsSystem, SolarSystemObjectData};
+
+// THE BODY THIS OBJECT ORBITS, SUCH AS EARTH FOR THE MOON
+#[derive(Reflect, Component, Debug)]
+#[reflect(Component)]
+pub struct OrbitParent {
+    pub entity: Entity
+}
+
+// an empty parent is never used directly, but reflection needs a way to build one
+impl FromWorld for OrbitParent {
+    fn from_world(_world: &mut World) -> OrbitParent {
+        OrbitParent { entity: Entity::from_raw(u32::MAX) }
+    }
+}
+
+// position, speed and osculating orbit of an object relative to its parent,
+// kept up to date each frame for anything that wants to report orbital quantities
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct ParentRelativeOrbit {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    pub distance_m: f64,
+    pub speed_m_s: f64,
+    pub gravitational_parameter: f64,  // G * (M + m), m3 s-2
+    #[reflect(ignore)]
+    pub elements: OrbitalElements
+}
+
+impl ParentRelativeOrbit {
+    /// Time for one orbit in seconds, or `None` if the object is escaping its parent
+    pub fn period_seconds(&self) -> Option<f64> {
+        self.elements.period_seconds(self.gravitational_parameter)
+    }
+}
+
+pub struct OrbitPlugin;
+
+impl Plugin for OrbitPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OrbitParent>()
+            .register_type::<ParentRelativeOrbit>()
+            .add_system_to_stage(PhysicsStage::Interpolate, update_parent_relative_orbits
+                .after(PhysicsSystem::Sync));
+    }
+}
+
+fn update_parent_relative_orbits(
+    mut commands: Commands,
+    mut child_query: Query<(Entity, &OrbitParent, Option<&mut ParentRelativeOrbit>)>,
+    object_query: Query<&SolarSystemObjectData>
+) {
+    for (entity, orbit_parent, relative_orbit) in &mut child_query {
+        let (Ok(child), Ok(parent)) =
+            (object_query.get(entity), object_query.get(orbit_parent.entity)) else { continue };
+        let (position, velocity) = child.to_body().relative_to(&parent.to_body());
+        let gravitational_parameter: f64 = GRAV_CONST * (child.mass_kg + parent.mass_kg);
+        let orbit: ParentRelativeOrbit = ParentRelativeOrbit {
+            position,
+            velocity,
+            distance_m: position.length(),
+            speed_m_s: velocity.length(),
+            gravitational_parameter,
+            elements: OrbitalElements::from_state_vectors(
+                world_to_ecliptic(position),
+                world_to_ecliptic(velocity),
+                gravitational_parameter
+            )
+        };
+        match relative_orbit {
+            Some(mut relative_orbit) => *relative_orbit = orbit,
+            None => { commands.entity(entity).insert(orbit); }
+        }
+    }
+}
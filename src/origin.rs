@@ -0,0 +1,92 @@
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::{CameraFlight, CameraFollow, MainCamera, ManualCameraController, PhysicsStage, PhysicsSystem};
+
+pub const RECENTRE_DISTANCE: f32 = 10.0; // render units the camera's focus may stray before the origin follows
+
+// WHERE RENDER COORDINATES ARE MEASURED FROM
+// transforms are f32, so they are kept relative to a point near the camera's focus
+// rather than to the barycentre; the origin itself is in render units from the
+// barycentre, in f64 like the physics
+#[derive(Reflect, Resource, Default)]
+pub struct RenderOrigin {
+    pub position: DVec3
+}
+
+impl RenderOrigin {
+    /// Translation to draw something at `position`, in render units from the barycentre
+    pub fn to_render(&self, position: DVec3) -> Vec3 {
+        (position - self.position).as_vec3()
+    }
+
+    /// Where a translation is, in render units from the barycentre
+    pub fn to_absolute(&self, translation: Vec3) -> DVec3 {
+        self.position + translation.as_dvec3()
+    }
+}
+
+// an object that stays put in the solar system, such as a light, placed by
+// its position in render units from the barycentre
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct FixedInSpace {
+    pub position: DVec3
+}
+
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<RenderOrigin>()
+            .init_resource::<RenderOrigin>()
+            .register_type::<FixedInSpace>()
+            .add_system_to_stage(PhysicsStage::Interpolate, recentre_render_origin.before(PhysicsSystem::Place))
+            .add_system_to_stage(PhysicsStage::Interpolate, place_fixed_objects.after(recentre_render_origin));
+    }
+}
+
+/// Move the origin onto the main camera's focus once the focus has strayed
+/// too far from it, and move the cameras and anything else holding render
+/// coordinates back by the same amount, so nothing on screen jumps. Bodies
+/// are placed from the new origin straight afterwards.
+fn recentre_render_origin(
+    mut render_origin: ResMut<RenderOrigin>,
+    mut main_camera_query: Query<(&mut Transform, &ManualCameraController), With<MainCamera>>,
+    mut camera_query: Query<&mut Transform, (With<Camera>, Without<MainCamera>)>,
+    mut flight_query: Query<&mut CameraFlight>,
+    mut follow_query: Query<&mut CameraFollow>
+) {
+    let Ok((mut camera, controller)) = main_camera_query.get_single_mut() else { return };
+    let focus: Vec3 = camera.translation + camera.forward() * controller.focus_distance;
+    if focus.length() <= RECENTRE_DISTANCE {
+        return;
+    }
+
+    render_origin.position = render_origin.to_absolute(focus);
+    camera.translation -= focus;
+    for mut transform in &mut camera_query {
+        transform.translation -= focus;
+    }
+    for mut flight in &mut flight_query {
+        flight.start_translation -= focus;
+        flight.start_focus -= focus;
+    }
+    for mut follow in &mut follow_query {
+        if let Some(last_position) = follow.last_position.as_mut() {
+            *last_position -= focus;
+        }
+    }
+}
+
+fn place_fixed_objects(
+    mut object_query: Query<(&mut Transform, &FixedInSpace)>,
+    render_origin: Res<RenderOrigin>
+) {
+    for (mut transform, fixed) in &mut object_query {
+        let translation: Vec3 = render_origin.to_render(fixed.position);
+        if transform.translation != translation {
+            transform.translation = translation;
+        }
+    }
+}
@@ -0,0 +1,341 @@
+use std::collections::HashMap;
+
+use bevy::ecs::schedule::ShouldRun;
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::nbody::{compute_accelerations, Body, IntegratorMethod, SystemState};
+use crate::{
+    DisplayScale, OrbitParent, ParentRelativeOrbit, RenderOrigin, SimulationClock, SolarSystemObjectData, TimeWarp
+};
+
+pub const PHYSICS_SUBSTEP_SECONDS: f64 = 1000.0; // simulated seconds per physics step, at most
+pub const MIN_PHYSICS_SUBSTEPS_PER_ORBIT: f64 = 200.0; // around the shortest orbit, for stability
+pub const MAX_PHYSICS_SUBSTEP_HALVINGS: f64 = 16.0; // floor for the step if an orbit degenerates
+pub const MAX_PHYSICS_SUBSTEPS_PER_FRAME: u32 = 1000;
+
+// STAGES THAT MAKE UP THE PHYSICS SCHEDULE
+// Step runs zero or more times a frame, once per fixed substep,
+// Interpolate runs once a frame afterwards to place the rendered objects
+#[derive(StageLabel)]
+pub enum PhysicsStage {
+    Step,
+    Interpolate
+}
+
+// labels so other plugins can order their systems around the physics step,
+// copying its results into the objects and placing the rendered objects
+#[derive(SystemLabel)]
+pub enum PhysicsSystem {
+    Move,
+    Sync,
+    Place
+}
+
+bevy::reflect::impl_reflect_value!(IntegratorMethod(Debug, PartialEq, Default));
+
+// SELECT WHICH INTEGRATOR MOVES THE SOLAR SYSTEM
+#[derive(Reflect, Resource, Default)]
+pub struct IntegratorSetting {
+    pub method: IntegratorMethod
+}
+
+// THE HEADLESS N-BODY STATE THAT DRIVES EVERY SOLAR SYSTEM OBJECT
+#[derive(Resource, Default)]
+pub struct NBodySimulation {
+    pub state: SystemState
+}
+
+// RESOURCE TO TRACK SIMULATED TIME WAITING TO BE INTEGRATED
+// substep_seconds is picked from the orbits each frame, at most base_substep_seconds;
+// achieved_warp is the simulated seconds per real second actually run, which
+// falls below the time warp once max_substeps_per_frame is reached
+#[derive(Reflect, Resource)]
+pub struct PhysicsClock {
+    pub substep_seconds: f64,
+    pub base_substep_seconds: f64,
+    pub min_substeps_per_orbit: f64,
+    pub max_substeps_per_frame: u32,
+    pub accumulator_seconds: f64,
+    pub substeps_this_frame: u32,
+    pub achieved_warp: f64,
+    pub reversed: bool
+}
+
+impl Default for PhysicsClock {
+    fn default() -> PhysicsClock {
+        PhysicsClock {
+            substep_seconds: PHYSICS_SUBSTEP_SECONDS,
+            base_substep_seconds: PHYSICS_SUBSTEP_SECONDS,
+            min_substeps_per_orbit: MIN_PHYSICS_SUBSTEPS_PER_ORBIT,
+            max_substeps_per_frame: MAX_PHYSICS_SUBSTEPS_PER_FRAME,
+            accumulator_seconds: 0.0,
+            substeps_this_frame: 0,
+            achieved_warp: 0.0,
+            reversed: false
+        }
+    }
+}
+
+impl PhysicsClock {
+    /// How far the simulation is between the last two physics states, 0 to 1
+    pub fn interpolation_alpha(&self) -> f64 {
+        (self.accumulator_seconds / self.substep_seconds).clamp(0.0, 1.0)
+    }
+
+    /// The signed time one substep moves the simulation by
+    pub fn step_seconds(&self) -> f64 {
+        if self.reversed { -self.substep_seconds } else { self.substep_seconds }
+    }
+
+    /// The longest substep that still takes `min_substeps_per_orbit` steps
+    /// around the shortest orbit. It only halves from the base step and never
+    /// depends on the warp, so a high warp runs more substeps rather than
+    /// longer ones, and running backwards retraces the same steps.
+    pub fn stable_substep(&self, shortest_period_seconds: Option<f64>) -> f64 {
+        let bound: f64 = shortest_period_seconds
+            .map_or(self.base_substep_seconds, |period| period / self.min_substeps_per_orbit);
+        let halvings: f64 = (self.base_substep_seconds / bound).log2().ceil()
+            .clamp(0.0, MAX_PHYSICS_SUBSTEP_HALVINGS);
+        self.base_substep_seconds * (-halvings).exp2()
+    }
+}
+
+// index of an object's body within NBodySimulation
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct PhysicsBody {
+    pub index: usize
+}
+
+// position and velocity of an object at the end of the previous physics step,
+// used to interpolate its transform and readouts between steps
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct PreviousPhysicsPosition {
+    pub position: DVec3,
+    pub velocity: DVec3
+}
+
+impl PreviousPhysicsPosition {
+    /// `current` moved back to `alpha` of the way from the previous step to it
+    pub fn interpolate(&self, current: &Body, alpha: f64) -> Body {
+        Body {
+            position: self.position.lerp(current.position, alpha),
+            velocity: self.velocity.lerp(current.velocity, alpha),
+            ..current.clone()
+        }
+    }
+}
+
+// SENT WHEN BODIES ARE CHANGED OUTSIDE THE INTEGRATOR, SUCH AS A MASS EDIT,
+// so conserved totals jump for a reason other than drift
+pub struct PhysicsBodiesEdited;
+
+pub struct PhysicsPlugin;
+
+impl Plugin for PhysicsPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<IntegratorSetting>()
+            .init_resource::<IntegratorSetting>()
+            .init_resource::<NBodySimulation>()
+            .register_type::<PhysicsClock>()
+            .init_resource::<PhysicsClock>()
+            .register_type::<PhysicsBody>()
+            .register_type::<PreviousPhysicsPosition>()
+            .add_event::<PhysicsBodiesEdited>()
+            .add_stage_before(
+                CoreStage::Update,
+                PhysicsStage::Step,
+                SystemStage::parallel().with_run_criteria(run_physics_substep)
+            )
+            .add_stage_after(
+                PhysicsStage::Step,
+                PhysicsStage::Interpolate,
+                SystemStage::parallel()
+            )
+            .add_system_to_stage(CoreStage::PreUpdate, accumulate_physics_time)
+            .add_system_to_stage(CoreStage::PreUpdate, remove_despawned_physics_bodies
+                .before(register_physics_bodies))
+            .add_system_to_stage(CoreStage::PreUpdate, register_physics_bodies)
+            .add_system_to_stage(CoreStage::PreUpdate, apply_mass_edits)
+            .add_system_to_stage(PhysicsStage::Step, record_previous_physics_positions
+                .before(PhysicsSystem::Move))
+            .add_system_to_stage(PhysicsStage::Step, step_n_body_simulation
+                .label(PhysicsSystem::Move))
+            .add_system_to_stage(PhysicsStage::Interpolate, sync_solar_system_objects
+                .label(PhysicsSystem::Sync))
+            .add_system_to_stage(PhysicsStage::Interpolate, interpolate_object_transforms
+                .label(PhysicsSystem::Place));
+    }
+}
+
+/// Add the frame's worth of warped time, plus any single steps queued while
+/// paused, to the accumulator. Anything beyond what `max_substeps_per_frame`
+/// can integrate is dropped, so a high warp or a frame hitch slows the
+/// simulation down, as shown by `achieved_warp`, instead of stretching the
+/// step until it is unstable.
+fn accumulate_physics_time(
+    mut physics_clock: ResMut<PhysicsClock>,
+    mut time_warp: ResMut<TimeWarp>,
+    orbit_query: Query<&ParentRelativeOrbit>,
+    time: Res<Time>
+) {
+    let shortest_period: Option<f64> = orbit_query.iter()
+        .filter_map(|orbit| orbit.period_seconds())
+        .reduce(f64::min);
+    let substep_seconds: f64 = physics_clock.stable_substep(shortest_period);
+    if substep_seconds != physics_clock.substep_seconds {
+        // keep the same fraction of a step waiting, so the interpolation does not jump
+        physics_clock.accumulator_seconds = physics_clock.interpolation_alpha() * substep_seconds;
+        physics_clock.substep_seconds = substep_seconds;
+    }
+    physics_clock.reversed = time_warp.is_reversed();
+    let queued_seconds: f64 = time_warp.pending_steps as f64 * physics_clock.substep_seconds;
+    time_warp.pending_steps = 0;
+
+    let max_accumulated: f64 =
+        physics_clock.substep_seconds * physics_clock.max_substeps_per_frame as f64;
+    let room_seconds: f64 = (max_accumulated - physics_clock.accumulator_seconds - queued_seconds).max(0.0);
+    let warped_seconds: f64 = time_warp.simulated_seconds(time.delta_seconds_f64()).abs().min(room_seconds);
+    physics_clock.accumulator_seconds =
+        (physics_clock.accumulator_seconds + queued_seconds + warped_seconds).min(max_accumulated);
+    physics_clock.achieved_warp = if time.delta_seconds_f64() > 0.0 {
+        warped_seconds / time.delta_seconds_f64()
+    } else {
+        0.0
+    };
+    physics_clock.substeps_this_frame = 0;
+}
+
+/// Keep running the physics stage until less than one substep is left
+fn run_physics_substep(mut physics_clock: ResMut<PhysicsClock>) -> ShouldRun {
+    if physics_clock.accumulator_seconds >= physics_clock.substep_seconds
+        && physics_clock.substeps_this_frame < physics_clock.max_substeps_per_frame {
+        physics_clock.accumulator_seconds -= physics_clock.substep_seconds;
+        physics_clock.substeps_this_frame += 1;
+        ShouldRun::YesAndCheckAgain
+    } else {
+        ShouldRun::No
+    }
+}
+
+/// Hand any newly spawned solar system object over to the n-body simulation
+fn register_physics_bodies(
+    mut commands: Commands,
+    mut simulation: ResMut<NBodySimulation>,
+    object_query: Query<(Entity, &SolarSystemObjectData), Without<PhysicsBody>>
+) {
+    for (entity, object_data) in &object_query {
+        let index: usize = simulation.state.add_body(object_data.to_body());
+        commands.entity(entity).insert((
+            PhysicsBody { index },
+            PreviousPhysicsPosition {
+                position: simulation.state.bodies[index].position,
+                velocity: simulation.state.bodies[index].velocity
+            }
+        ));
+    }
+}
+
+/// Drop the bodies of despawned objects from the n-body simulation, so they
+/// stop pulling on the rest. The remaining bodies are packed down in the
+/// order of their old indices and each object's index is moved with its body.
+fn remove_despawned_physics_bodies(
+    mut simulation: ResMut<NBodySimulation>,
+    mut object_query: Query<&mut PhysicsBody>
+) {
+    if object_query.iter().len() == simulation.state.bodies.len() {
+        return;
+    }
+    let mut kept: Vec<Mut<PhysicsBody>> = object_query.iter_mut().collect();
+    kept.sort_by_key(|physics_body| physics_body.index);
+    let mut bodies: Vec<Body> = Vec::with_capacity(kept.len());
+    for physics_body in &mut kept {
+        bodies.push(simulation.state.bodies[physics_body.index].clone());
+        physics_body.index = bodies.len() - 1;
+    }
+    compute_accelerations(&mut bodies);
+    simulation.state.bodies = bodies;
+}
+
+/// Push masses edited in the inspector into the n-body simulation. Positions
+/// and speeds are copied from the simulation every frame, so the mass is the
+/// only thing that can be edited this way.
+fn apply_mass_edits(
+    mut simulation: ResMut<NBodySimulation>,
+    mut edited_writer: EventWriter<PhysicsBodiesEdited>,
+    object_query: Query<(&PhysicsBody, &SolarSystemObjectData), Changed<SolarSystemObjectData>>
+) {
+    let edited: Vec<(usize, f64)> = object_query.iter()
+        .filter(|(physics_body, object_data)| simulation.state.bodies[physics_body.index].mass_kg != object_data.mass_kg)
+        .map(|(physics_body, object_data)| (physics_body.index, object_data.mass_kg))
+        .collect();
+    if edited.is_empty() {
+        return;
+    }
+    for (index, mass_kg) in edited {
+        simulation.state.bodies[index].mass_kg = mass_kg;
+    }
+    compute_accelerations(&mut simulation.state.bodies);
+    edited_writer.send(PhysicsBodiesEdited);
+}
+
+fn record_previous_physics_positions(
+    mut object_query: Query<(&PhysicsBody, &mut PreviousPhysicsPosition)>,
+    simulation: Res<NBodySimulation>
+) {
+    for (physics_body, mut previous) in &mut object_query {
+        previous.position = simulation.state.bodies[physics_body.index].position;
+        previous.velocity = simulation.state.bodies[physics_body.index].velocity;
+    }
+}
+
+/// Advance the n-body simulation by one fixed substep, backwards when the time
+/// warp is reversed, with the integrator chosen in `IntegratorSetting`, and
+/// the simulation clock with it
+fn step_n_body_simulation(
+    mut simulation: ResMut<NBodySimulation>,
+    mut clock: ResMut<SimulationClock>,
+    integrator_setting: Res<IntegratorSetting>,
+    physics_clock: Res<PhysicsClock>
+) {
+    simulation.state.integrator = integrator_setting.method;
+    simulation.state.step(physics_clock.step_seconds());
+    clock.advance(physics_clock.step_seconds());
+}
+
+/// Mirror the simulation's speed, position and acceleration into each object
+fn sync_solar_system_objects(
+    mut object_query: Query<(&PhysicsBody, &mut SolarSystemObjectData)>,
+    simulation: Res<NBodySimulation>
+) {
+    for (physics_body, mut object_data) in &mut object_query {
+        object_data.set_from_body(&simulation.state.bodies[physics_body.index]);
+    }
+}
+
+/// Place every object between its last two physics positions, so motion
+/// stays smooth whatever the frame rate, laid out by the display scale and
+/// drawn relative to the render origin
+fn interpolate_object_transforms(
+    mut object_query: Query<(Entity, &PhysicsBody, &PreviousPhysicsPosition, Option<&OrbitParent>, &mut Transform)>,
+    simulation: Res<NBodySimulation>,
+    physics_clock: Res<PhysicsClock>,
+    display_scale: Res<DisplayScale>,
+    render_origin: Res<RenderOrigin>
+) {
+    let alpha: f64 = physics_clock.interpolation_alpha();
+    let bodies: HashMap<Entity, (DVec3, Option<Entity>)> = object_query.iter()
+        .map(|(entity, physics_body, previous, parent, _)| {
+            let current: DVec3 = simulation.state.bodies[physics_body.index].position;
+            (entity, (previous.position.lerp(current, alpha), parent.map(|parent| parent.entity)))
+        })
+        .collect();
+    let placed: HashMap<Entity, DVec3> = display_scale.layout(&bodies);
+    for (entity, _, _, _, mut transform) in &mut object_query {
+        if let Some(position) = placed.get(&entity) {
+            transform.translation = render_origin.to_render(*position);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+
+use crate::{BodyLabel, BodyRegistry, BodySelected, DisplayScale, InsetFrame, SolarSystemObjectData};
+
+pub const PICK_MIN_SCREEN_RADIUS: f32 = 8.0; // pixels, so far away bodies can still be clicked
+pub const CLICK_DRAG_TOLERANCE: f32 = 4.0; // pixels the cursor may move before a click becomes a drag
+
+pub struct BodyPickingPlugin;
+
+impl Plugin for BodyPickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(pick_clicked_body)
+            .add_system(body_label_clicks);
+    }
+}
+
+/// Radius of a body's model as drawn, from the bounding boxes of the meshes
+/// its scene spawned, measured from `centre`
+pub fn display_radius(
+    entity: Entity,
+    centre: Vec3,
+    children_query: &Query<&Children>,
+    mesh_query: &Query<(&Aabb, &GlobalTransform)>
+) -> f32 {
+    let mut radius: f32 = 0.0;
+    let mut stack: Vec<Entity> = vec![entity];
+    while let Some(entity) = stack.pop() {
+        if let Ok((aabb, transform)) = mesh_query.get(entity) {
+            let (scale, _, _) = transform.to_scale_rotation_translation();
+            let mesh_centre: Vec3 = transform.transform_point(aabb.center.into());
+            let extent: f32 = (Vec3::from(aabb.half_extents) * scale).length();
+            radius = radius.max(mesh_centre.distance(centre) + extent);
+        }
+        if let Ok(children) = children_query.get(entity) {
+            stack.extend(children.iter());
+        }
+    }
+    radius
+}
+
+/// Where `cursor` is inside `camera`'s viewport, measured from its bottom left
+/// corner like `Window::cursor_position`, or None when it is outside
+pub fn cursor_in_viewport(camera: &Camera, window: &Window, cursor: Vec2) -> Option<Vec2> {
+    let Some(viewport) = &camera.viewport else { return Some(cursor) };
+    let scale: f32 = window.scale_factor() as f32;
+    let size: Vec2 = viewport.physical_size.as_vec2() / scale;
+    // viewports are placed from the top left corner
+    let top_left: Vec2 = viewport.physical_position.as_vec2() / scale;
+    let inside: Vec2 = cursor - Vec2::new(top_left.x, window.height() - top_left.y - size.y);
+    (inside.cmpge(Vec2::ZERO).all() && inside.cmple(size).all()).then_some(inside)
+}
+
+/// Distance along a ray to where it first meets a sphere, if it does
+fn ray_sphere_distance(origin: Vec3, direction: Vec3, centre: Vec3, radius: f32) -> Option<f32> {
+    let to_centre: Vec3 = centre - origin;
+    let along: f32 = to_centre.dot(direction);
+    let miss_squared: f32 = to_centre.length_squared() - along * along;
+    if along < 0.0 || miss_squared > radius * radius {
+        return None;
+    }
+    Some(along - (radius * radius - miss_squared).sqrt())
+}
+
+/// Select the body under the cursor when the left button is clicked rather
+/// than dragged. A ray from the camera is tested against each body as a
+/// sphere as big as its drawn radius or its model, whichever is larger, and
+/// any body within `PICK_MIN_SCREEN_RADIUS` of the cursor on screen counts
+/// too, so planets a pixel wide can still be picked. The nearest hit wins.
+/// Clicks inside the inset pick through the inset's camera.
+#[allow(clippy::too_many_arguments)]
+fn pick_clicked_body(
+    mut selected_events: EventWriter<BodySelected>,
+    mut press_position: Local<Option<Vec2>>,
+    windows: Res<Windows>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    interaction_query: Query<&Interaction, Without<InsetFrame>>,
+    camera_query: Query<(&Camera, &Transform), With<Camera3d>>,
+    body_query: Query<(Entity, &Transform, &SolarSystemObjectData)>,
+    children_query: Query<&Children>,
+    mesh_query: Query<(&Aabb, &GlobalTransform)>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>
+) {
+    let Some(window) = windows.get_primary() else { return };
+    let Some(cursor) = window.cursor_position() else { return };
+    if mouse_buttons.just_pressed(MouseButton::Left) {
+        // clicks on buttons and labels are handled by the UI
+        let over_ui: bool = interaction_query.iter().any(|interaction| *interaction != Interaction::None);
+        *press_position = if over_ui { None } else { Some(cursor) };
+    }
+    if !mouse_buttons.just_released(MouseButton::Left) {
+        return;
+    }
+    let Some(pressed_at) = press_position.take() else { return };
+    if pressed_at.distance(cursor) > CLICK_DRAG_TOLERANCE {
+        return;
+    }
+
+    // the camera drawn on top wins where viewports overlap
+    let Some((camera, camera_transform, cursor)) = camera_query.iter()
+        .filter(|(camera, _)| camera.is_active)
+        .filter_map(|(camera, transform)| cursor_in_viewport(camera, window, cursor).map(|cursor| (camera, transform, cursor)))
+        .max_by_key(|(camera, _, _)| camera.priority) else { return };
+    // cameras have no parent, so their transform is already global
+    let camera_global: GlobalTransform = GlobalTransform::from(*camera_transform);
+    let Some(ray) = camera.viewport_to_world(&camera_global, cursor) else { return };
+
+    let nearest: Option<(Entity, f32)> = body_query.iter()
+        .filter_map(|(entity, transform, object_data)| {
+            let centre: Vec3 = transform.translation;
+            let radius: f32 = display_scale.radius(object_data.radius_m)
+                .max(display_radius(entity, centre, &children_query, &mesh_query));
+            let hit: Option<f32> = ray_sphere_distance(ray.origin, ray.direction, centre, radius).or_else(|| {
+                camera.world_to_viewport(&camera_global, centre)
+                    .filter(|point| point.distance(cursor) <= PICK_MIN_SCREEN_RADIUS)
+                    .map(|_| centre.distance(ray.origin))
+            });
+            hit.map(|distance| (entity, distance))
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    if let Some(body) = nearest.and_then(|(entity, _)| registry.find_by_entity(entity)) {
+        selected_events.send(BodySelected { body: body.id });
+    }
+}
+
+fn body_label_clicks(
+    mut selected_events: EventWriter<BodySelected>,
+    label_query: Query<(&Interaction, &BodyLabel), Changed<Interaction>>,
+    registry: Res<BodyRegistry>
+) {
+    for (interaction, label) in &label_query {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let Some(body) = registry.find_by_entity(label.body) {
+            selected_events.send(BodySelected { body: body.id });
+        }
+    }
+}
@@ -0,0 +1,270 @@
+use bevy::core_pipeline::clear_color::ClearColorConfig;
+use bevy::prelude::*;
+use bevy::render::camera::Viewport;
+use bevy::window::{WindowResized, WindowScaleFactorChanged};
+
+use crate::{
+    BodyId, BodyRegistry, CurrentPlanetForCamera, DisplayScale, InsetTargetButton, InsetTargetText, InsetViewButton,
+    MapCamera, MapSystem, MapView, SolarSystemObjectData, CAMERA_NEAR_PLANE, SELECTED_BUTTON_COLOR
+};
+
+pub const INSET_SIZE: Vec2 = Vec2::new(360.0, 240.0); // logical pixels
+pub const INSET_MARGIN: f32 = 10.0; // from the bottom right corner of the window
+pub const INSET_BORDER: f32 = 2.0;
+pub const INSET_BACKGROUND_COLOR: Color = Color::rgb(0.05, 0.05, 0.1);
+pub const INSET_ORBIT_SPEED: f32 = 0.2; // radians per second the close-up turns around its body
+pub const INSET_VIEWING_HEIGHT: f32 = 0.3; // of the close-up's distance, above the ecliptic
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InsetView {
+    #[default]
+    Off,
+    CloseUp,
+    Map
+}
+
+// A SECOND VIEW IN THE BOTTOM RIGHT CORNER
+// either a close-up of one body or the map centred on it, chosen from the control panel;
+// with no target of its own the inset shows the body the main camera is focused on
+#[derive(Reflect, Resource, Default)]
+pub struct PictureInPicture {
+    pub view: InsetView,
+    pub target: Option<BodyId>
+}
+
+impl PictureInPicture {
+    /// The body shown in the inset, its own target while that is still registered
+    pub fn target_body(&self, current_planet: &CurrentPlanetForCamera, registry: &BodyRegistry) -> Option<BodyId> {
+        self.target
+            .filter(|id| registry.get(*id).is_some())
+            .or(current_planet.body)
+    }
+
+    /// Move the target `step` places through the registered bodies, where the
+    /// place before the first body means following the main camera's focus
+    pub fn step_target(&mut self, step: i32, registry: &BodyRegistry) {
+        let choices: Vec<Option<BodyId>> = std::iter::once(None)
+            .chain(registry.iter().map(|body| Some(body.id)))
+            .collect();
+        let index: i32 = choices.iter().position(|choice| *choice == self.target).unwrap_or(0) as i32;
+        self.target = choices[(index + step).rem_euclid(choices.len() as i32) as usize];
+    }
+}
+
+// the camera drawing the close-up
+#[derive(Component)]
+pub struct InsetCamera;
+
+// the border and backdrop the inset is drawn over, which also keeps the mouse
+// from working the main camera over it
+#[derive(Component)]
+pub struct InsetFrame;
+
+pub struct PictureInPicturePlugin;
+
+impl Plugin for PictureInPicturePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<InsetView>()
+            .register_type::<PictureInPicture>()
+            .init_resource::<PictureInPicture>()
+            .add_startup_system(add_inset)
+            .add_system(inset_buttons)
+            .add_system(show_inset.after(inset_buttons))
+            .add_system(frame_inset_close_up.after(show_inset))
+            .add_system(centre_inset_map.after(MapSystem::Controls).before(MapSystem::Draw))
+            .add_system(show_inset_target.after(inset_buttons));
+    }
+}
+
+/// The inset's viewport in the bottom right corner of `window`
+pub fn inset_viewport(window: &Window) -> Viewport {
+    let scale: f32 = window.scale_factor() as f32;
+    let window_size: Vec2 = Vec2::new(window.physical_width() as f32, window.physical_height() as f32);
+    // a viewport past the edge of the window is rejected by the renderer
+    let size: Vec2 = (INSET_SIZE * scale).min(window_size).max(Vec2::ONE);
+    // viewports are placed from the top left corner
+    let position: Vec2 = (window_size - size - Vec2::splat(INSET_MARGIN * scale)).max(Vec2::ZERO);
+    Viewport {
+        physical_position: position.as_uvec2(),
+        physical_size: size.as_uvec2(),
+        ..default()
+    }
+}
+
+fn add_inset(mut commands: Commands) {
+    commands.spawn((
+        Camera3dBundle {
+            camera: Camera {
+                is_active: false,
+                priority: 2,
+                ..default()
+            },
+            projection: PerspectiveProjection {
+                near: CAMERA_NEAR_PLANE,
+                ..default()
+            }.into(),
+            // drawn over the main view, so it must not clear the rest of the window
+            camera_3d: Camera3d {
+                clear_color: ClearColorConfig::None,
+                ..default()
+            },
+            ..default()
+        },
+        UiCameraConfig { show_ui: false },
+        InsetCamera
+    )).insert(Name::new("Inset Camera"));
+
+    // the UI is drawn before the inset, so this shows around and behind it
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect {
+                    right: Val::Px(INSET_MARGIN - INSET_BORDER),
+                    bottom: Val::Px(INSET_MARGIN - INSET_BORDER),
+                    ..default()
+                },
+                size: Size::new(
+                    Val::Px(INSET_SIZE.x + 2.0 * INSET_BORDER),
+                    Val::Px(INSET_SIZE.y + 2.0 * INSET_BORDER)
+                ),
+                padding: UiRect::all(Val::Px(INSET_BORDER)),
+                display: Display::None,
+                ..default()
+            },
+            background_color: SELECTED_BUTTON_COLOR.into(),
+            ..default()
+        },
+        Interaction::default(),
+        InsetFrame
+    )).insert(Name::new("Inset Frame")).with_children(|frame| {
+        frame.spawn(NodeBundle {
+            style: Style {
+                size: Size::new(Val::Percent(100.0), Val::Percent(100.0)),
+                ..default()
+            },
+            background_color: INSET_BACKGROUND_COLOR.into(),
+            ..default()
+        });
+    });
+}
+
+fn inset_buttons(
+    mut picture_in_picture: ResMut<PictureInPicture>,
+    view_query: Query<(&Interaction, &InsetViewButton), Changed<Interaction>>,
+    target_query: Query<(&Interaction, &InsetTargetButton), Changed<Interaction>>,
+    registry: Res<BodyRegistry>
+) {
+    for (interaction, button) in &view_query {
+        if *interaction == Interaction::Clicked {
+            picture_in_picture.view = button.view;
+        }
+    }
+    for (interaction, button) in &target_query {
+        if *interaction == Interaction::Clicked {
+            picture_in_picture.step_target(button.step, &registry);
+        }
+    }
+}
+
+/// Turn on the camera for the chosen view and put the frame behind it. The
+/// map is drawn by its own camera, which the map view moves into the inset.
+/// The viewport is redone when the window is resized or rescaled.
+fn show_inset(
+    mut map_view: ResMut<MapView>,
+    mut camera_query: Query<&mut Camera, With<InsetCamera>>,
+    mut frame_query: Query<&mut Style, With<InsetFrame>>,
+    mut resized: EventReader<WindowResized>,
+    mut rescaled: EventReader<WindowScaleFactorChanged>,
+    picture_in_picture: Res<PictureInPicture>,
+    windows: Res<Windows>
+) {
+    let window_changed: bool = resized.iter().count() + rescaled.iter().count() > 0;
+    if !picture_in_picture.is_changed() && !map_view.is_changed() && !window_changed {
+        return;
+    }
+    let map_inset: bool = picture_in_picture.view == InsetView::Map;
+    if map_view.inset != map_inset {
+        map_view.inset = map_inset;
+    }
+    let Some(window) = windows.get_primary() else { return };
+    for mut camera in &mut camera_query {
+        camera.is_active = picture_in_picture.view == InsetView::CloseUp;
+        camera.viewport = Some(inset_viewport(window));
+    }
+    // over the full screen map there is no map inset to frame
+    let framed: bool = match picture_in_picture.view {
+        InsetView::Off => false,
+        InsetView::CloseUp => true,
+        InsetView::Map => !map_view.enabled
+    };
+    for mut style in &mut frame_query {
+        style.display = if framed { Display::Flex } else { Display::None };
+    }
+}
+
+/// Circle the inset's target slowly, from a little above it, as far out as
+/// the automatic camera would be
+#[allow(clippy::too_many_arguments)]
+fn frame_inset_close_up(
+    mut orbit_angle: Local<f32>,
+    mut camera_query: Query<&mut Transform, With<InsetCamera>>,
+    body_query: Query<(&Transform, &SolarSystemObjectData), Without<InsetCamera>>,
+    picture_in_picture: Res<PictureInPicture>,
+    current_planet: Res<CurrentPlanetForCamera>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>,
+    time: Res<Time>
+) {
+    if picture_in_picture.view != InsetView::CloseUp {
+        return;
+    }
+    let Some(entity) = picture_in_picture.target_body(&current_planet, &registry)
+        .and_then(|id| registry.entity(id)) else { return };
+    let Ok((body_transform, object_data)) = body_query.get(entity) else { return };
+
+    *orbit_angle = (*orbit_angle + INSET_ORBIT_SPEED * time.delta_seconds()) % std::f32::consts::TAU;
+    let distance: f32 = object_data.optimal_camera_distance * display_scale.radius(object_data.radius_m);
+    let offset: Vec3 = Vec3::new(orbit_angle.cos(), INSET_VIEWING_HEIGHT, orbit_angle.sin()) * distance;
+    for mut transform in &mut camera_query {
+        *transform = Transform::from_translation(body_transform.translation + offset)
+            .looking_at(body_transform.translation, Vec3::Y);
+    }
+}
+
+// as an inset the map keeps its zoom but stays over the inset's target
+fn centre_inset_map(
+    mut camera_query: Query<&mut Transform, With<MapCamera>>,
+    body_query: Query<&Transform, (With<SolarSystemObjectData>, Without<MapCamera>)>,
+    picture_in_picture: Res<PictureInPicture>,
+    current_planet: Res<CurrentPlanetForCamera>,
+    registry: Res<BodyRegistry>,
+    map_view: Res<MapView>
+) {
+    if !map_view.inset || map_view.enabled {
+        return;
+    }
+    let Some(body_transform) = picture_in_picture.target_body(&current_planet, &registry)
+        .and_then(|id| registry.entity(id))
+        .and_then(|entity| body_query.get(entity).ok()) else { return };
+    for mut transform in &mut camera_query {
+        transform.translation.x = body_transform.translation.x;
+        transform.translation.z = body_transform.translation.z;
+    }
+}
+
+fn show_inset_target(
+    mut text_query: Query<&mut Text, With<InsetTargetText>>,
+    picture_in_picture: Res<PictureInPicture>,
+    registry: Res<BodyRegistry>
+) {
+    let value: String = match picture_in_picture.target.and_then(|id| registry.get(id)) {
+        Some(body) => body.name.clone(),
+        None => "Focused".to_string()
+    };
+    for mut text in &mut text_query {
+        if text.sections[0].value != value {
+            text.sections[0].value = value.clone();
+        }
+    }
+}
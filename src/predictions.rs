@@ -0,0 +1,302 @@
+use std::sync::{Arc, Mutex};
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::view::NoFrustumCulling;
+use bevy::tasks::{AsyncComputeTaskPool, Task};
+
+use crate::nbody::{IntegratorMethod, SystemState, SECONDS_PER_DAY};
+use crate::{
+    trail_color, BodyRegistry, DisplayScale, IntegratorSetting, NBodySimulation, OrbitTrailSettings, PhysicsBody,
+    PhysicsClock, RenderOrigin, SimulationClock, SolarSystemObjectData, PHYSICS_SUBSTEP_SECONDS
+};
+
+pub const PREDICTION_SAMPLES: usize = 256;
+pub const DEFAULT_PREDICTION_DAYS: f64 = 365.0;
+pub const MAX_PREDICTION_STEPS: f64 = 50000.0; // longer horizons take longer steps instead
+pub const MAX_PREDICTION_STEP_SECONDS: f64 = 16000.0; // still ~150 steps per lunar orbit
+pub const PREDICTION_REFRESH_TIME: f32 = 0.5; // real seconds between predictions
+pub const PREDICTION_MAX_ALPHA: f32 = 0.5;
+
+// SETTINGS FOR THE PREDICTED ORBITS, P TOGGLES THEM
+// paths are drawn relative to the same reference body as the trails
+#[derive(Reflect, Resource)]
+pub struct OrbitPredictionSettings {
+    pub enabled: bool,
+    pub horizon_days: f64
+}
+
+impl Default for OrbitPredictionSettings {
+    fn default() -> OrbitPredictionSettings {
+        OrbitPredictionSettings {
+            enabled: true,
+            horizon_days: DEFAULT_PREDICTION_DAYS
+        }
+    }
+}
+
+// everything a prediction depends on besides the simulation state,
+// a change to any of them throws the current prediction away
+#[derive(Clone, Copy, PartialEq)]
+struct PredictionInputs {
+    horizon_days: f64,
+    integrator: IntegratorMethod,
+    reference: Option<usize>,
+    reversed: bool,
+    body_count: usize
+}
+
+// future positions of every body, indexed like the simulation's bodies
+struct PredictedPaths {
+    start_time: f64,
+    sample_seconds: f64, // signed, negative when predicting backwards
+    positions: Vec<Vec<DVec3>> // metres, relative to the reference body when there is one
+}
+
+impl PredictedPaths {
+    /// Integrate a copy of `state` over the horizon, recording every body's
+    /// position `PREDICTION_SAMPLES` times along the way
+    fn integrate(mut state: SystemState, inputs: PredictionInputs, start_time: f64) -> PredictedPaths {
+        let horizon: f64 = inputs.horizon_days * SECONDS_PER_DAY;
+        let step: f64 = (horizon / MAX_PREDICTION_STEPS).clamp(PHYSICS_SUBSTEP_SECONDS, MAX_PREDICTION_STEP_SECONDS);
+        let sample_seconds: f64 = horizon / PREDICTION_SAMPLES as f64;
+        let steps_per_sample: usize = (sample_seconds / step).ceil().max(1.0) as usize;
+        let direction: f64 = if inputs.reversed { -1.0 } else { 1.0 };
+        let dt: f64 = direction * sample_seconds / steps_per_sample as f64;
+
+        let mut positions: Vec<Vec<DVec3>> = vec![Vec::with_capacity(PREDICTION_SAMPLES); state.bodies.len()];
+        for _ in 0..PREDICTION_SAMPLES {
+            for _ in 0..steps_per_sample {
+                state.step(dt);
+            }
+            let reference_position: DVec3 = inputs.reference.map_or(DVec3::ZERO, |index| state.bodies[index].position);
+            for (path, body) in positions.iter_mut().zip(&state.bodies) {
+                path.push(body.position - reference_position);
+            }
+        }
+        PredictedPaths {
+            start_time,
+            sample_seconds: direction * sample_seconds,
+            positions
+        }
+    }
+
+    fn sample_time(&self, sample: usize) -> f64 {
+        self.start_time + (sample + 1) as f64 * self.sample_seconds
+    }
+}
+
+// THE LATEST FINISHED PREDICTION AND THE ONE BEING WORKED ON
+// the background task leaves its result in `finished` for the next frame to pick up
+#[derive(Resource)]
+pub struct OrbitPrediction {
+    paths: Option<PredictedPaths>,
+    inputs: Option<PredictionInputs>,
+    task: Option<Task<()>>,
+    finished: Arc<Mutex<Option<PredictedPaths>>>,
+    state_changed: bool,
+    refresh_timer: Timer
+}
+
+impl Default for OrbitPrediction {
+    fn default() -> OrbitPrediction {
+        OrbitPrediction {
+            paths: None,
+            inputs: None,
+            task: None,
+            finished: Arc::new(Mutex::new(None)),
+            state_changed: true,
+            refresh_timer: Timer::from_seconds(PREDICTION_REFRESH_TIME, TimerMode::Repeating)
+        }
+    }
+}
+
+// the line drawn ahead of one body
+#[derive(Component)]
+pub struct PredictedOrbit {
+    pub body: Entity
+}
+
+// marks a body that already has a predicted orbit
+#[derive(Component)]
+pub struct HasPredictedOrbit;
+
+pub struct OrbitPredictionPlugin;
+
+impl Plugin for OrbitPredictionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OrbitPredictionSettings>()
+            .init_resource::<OrbitPredictionSettings>()
+            .init_resource::<OrbitPrediction>()
+            .add_system(spawn_predicted_orbits)
+            .add_system(toggle_orbit_predictions)
+            .add_system(update_orbit_prediction)
+            .add_system(draw_predicted_orbits.after(update_orbit_prediction));
+    }
+}
+
+fn spawn_predicted_orbits(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    body_query: Query<(Entity, &SolarSystemObjectData), Without<HasPredictedOrbit>>,
+    path_query: Query<(Entity, &PredictedOrbit)>,
+    predicted_query: Query<(), With<HasPredictedOrbit>>
+) {
+    for (entity, object_data) in &body_query {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+                material: materials.add(StandardMaterial {
+                    base_color: trail_color(entity),
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                }),
+                // hidden until a prediction has finished
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            NoFrustumCulling,
+            PredictedOrbit { body: entity }
+        )).insert(Name::new(format!("{} Predicted Orbit", object_data.name)));
+        commands.entity(entity).insert(HasPredictedOrbit);
+    }
+    for (path_entity, path) in &path_query {
+        if predicted_query.get(path.body).is_err() {
+            commands.entity(path_entity).despawn_recursive();
+        }
+    }
+}
+
+fn toggle_orbit_predictions(
+    keyboard: Res<Input<KeyCode>>,
+    mut settings: ResMut<OrbitPredictionSettings>
+) {
+    if keyboard.just_pressed(KeyCode::P) {
+        settings.enabled = !settings.enabled;
+    }
+}
+
+/// Collect a finished prediction and start the next one. A new prediction is
+/// started straight away when the horizon, integrator, reference body or
+/// direction of time changes, or a body is added, and otherwise every
+/// `PREDICTION_REFRESH_TIME` while the simulation keeps changing.
+#[allow(clippy::too_many_arguments)]
+fn update_orbit_prediction(
+    mut prediction: ResMut<OrbitPrediction>,
+    body_query: Query<&PhysicsBody>,
+    simulation: Res<NBodySimulation>,
+    clock: Res<SimulationClock>,
+    physics_clock: Res<PhysicsClock>,
+    integrator_setting: Res<IntegratorSetting>,
+    settings: Res<OrbitPredictionSettings>,
+    trail_settings: Res<OrbitTrailSettings>,
+    registry: Res<BodyRegistry>,
+    time: Res<Time>
+) {
+    if simulation.is_changed() {
+        prediction.state_changed = true;
+    }
+    if prediction.task.as_ref().is_some_and(|task| task.is_finished()) {
+        prediction.task = None;
+        let finished: Option<PredictedPaths> = prediction.finished.lock().unwrap().take();
+        if finished.is_some() {
+            prediction.paths = finished;
+        }
+    }
+    if !settings.enabled || simulation.state.bodies.is_empty() {
+        return;
+    }
+
+    let inputs: PredictionInputs = PredictionInputs {
+        horizon_days: settings.horizon_days.max(0.0),
+        integrator: integrator_setting.method,
+        reference: trail_settings.reference
+            .and_then(|id| registry.entity(id))
+            .and_then(|entity| body_query.get(entity).ok())
+            .map(|physics_body| physics_body.index),
+        reversed: physics_clock.reversed,
+        body_count: simulation.state.bodies.len()
+    };
+    prediction.refresh_timer.tick(time.delta());
+    let refresh: bool = prediction.refresh_timer.finished() && prediction.state_changed && prediction.task.is_none();
+    if prediction.inputs == Some(inputs) && !refresh {
+        return;
+    }
+    if prediction.inputs != Some(inputs) {
+        // paths from the old inputs would be drawn in the wrong place,
+        // and dropping the task cancels it
+        prediction.paths = None;
+        prediction.task = None;
+    }
+
+    let mut state: SystemState = simulation.state.clone();
+    state.integrator = inputs.integrator;
+    let start_time: f64 = clock.tdb_seconds_since_j2000;
+    let finished: Arc<Mutex<Option<PredictedPaths>>> = Arc::new(Mutex::new(None));
+    let result: Arc<Mutex<Option<PredictedPaths>>> = finished.clone();
+    prediction.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+        *result.lock().unwrap() = Some(PredictedPaths::integrate(state, inputs, start_time));
+    }));
+    prediction.finished = finished;
+    prediction.inputs = Some(inputs);
+    prediction.state_changed = false;
+    prediction.refresh_timer.reset();
+}
+
+/// Draw each body's predicted path from its rendered position onwards,
+/// leaving out the part of the prediction it has already passed
+#[allow(clippy::too_many_arguments)]
+fn draw_predicted_orbits(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut path_query: Query<(&PredictedOrbit, &Handle<Mesh>, &mut Visibility)>,
+    body_query: Query<(&Transform, &PhysicsBody), With<SolarSystemObjectData>>,
+    prediction: Res<OrbitPrediction>,
+    clock: Res<SimulationClock>,
+    settings: Res<OrbitPredictionSettings>,
+    trail_settings: Res<OrbitTrailSettings>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>,
+    render_origin: Res<RenderOrigin>
+) {
+    let reference_entity: Option<Entity> = trail_settings.reference.and_then(|id| registry.entity(id));
+    let reference_translation: DVec3 = reference_entity
+        .and_then(|entity| body_query.get(entity).ok())
+        .map_or(-render_origin.position, |(transform, _)| transform.translation.as_dvec3());
+    let now: f64 = clock.tdb_seconds_since_j2000;
+
+    for (path, mesh_handle, mut visibility) in &mut path_query {
+        let ahead: Vec<DVec3> = match (&prediction.paths, body_query.get(path.body)) {
+            (Some(paths), Ok((_, physics_body))) if settings.enabled && Some(path.body) != reference_entity =>
+                paths.positions.get(physics_body.index).map_or(Vec::new(), |positions| {
+                    positions.iter()
+                        .enumerate()
+                        .filter(|(sample, _)| (paths.sample_time(*sample) - now) * paths.sample_seconds > 0.0)
+                        .map(|(_, position)| *position)
+                        .collect()
+                }),
+            _ => Vec::new()
+        };
+        let visible: bool = !ahead.is_empty();
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+        let (true, Some(mesh), Ok((body_transform, _))) =
+            (visible, meshes.get_mut(mesh_handle), body_query.get(path.body)) else { continue };
+
+        let mut positions: Vec<[f32; 3]> = vec![body_transform.translation.to_array()];
+        positions.extend(ahead.iter()
+            .map(|position| (reference_translation + display_scale.offset(*position)).as_vec3().to_array()));
+        let count: usize = positions.len();
+        let colors: Vec<[f32; 4]> = (0..count)
+            .map(|index| [1.0, 1.0, 1.0, PREDICTION_MAX_ALPHA * (1.0 - index as f32 / (count - 1) as f32)])
+            .collect();
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{OrbitParent, SolarSystemObjectData};
+
+// OPEN IDENTIFIER FOR A SOLAR SYSTEM OBJECT
+// handed out by the BodyRegistry, never reused within a run
+#[derive(Reflect, FromReflect, Component, Clone, Copy, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[reflect(Component)]
+pub struct BodyId(pub u32);
+
+#[derive(Reflect, Component, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[reflect(Component)]
+pub enum BodyCategory {
+    Star,
+    #[default]
+    Planet,
+    DwarfPlanet,
+    Moon,
+    Asteroid,
+    Spacecraft
+}
+
+#[derive(Debug, Clone)]
+pub struct RegisteredBody {
+    pub id: BodyId,
+    pub entity: Entity,
+    pub name: String,
+    pub category: BodyCategory,
+    pub parent: Option<Entity>
+}
+
+// RESOURCE MAPPING BODY IDS TO ENTITIES, NAMES AND CATEGORIES
+// bodies are kept in the order they were registered
+#[derive(Resource, Default)]
+pub struct BodyRegistry {
+    bodies: Vec<RegisteredBody>,
+    next_id: u32
+}
+
+impl BodyRegistry {
+    pub fn register(
+        &mut self,
+        entity: Entity,
+        name: String,
+        category: BodyCategory,
+        parent: Option<Entity>
+    ) -> BodyId {
+        let id: BodyId = BodyId(self.next_id);
+        self.next_id += 1;
+        self.bodies.push(RegisteredBody { id, entity, name, category, parent });
+        id
+    }
+
+    pub fn unregister(&mut self, id: BodyId) {
+        self.bodies.retain(|body| body.id != id);
+    }
+
+    pub fn get(&self, id: BodyId) -> Option<&RegisteredBody> {
+        self.bodies.iter().find(|body| body.id == id)
+    }
+
+    pub fn entity(&self, id: BodyId) -> Option<Entity> {
+        self.get(id).map(|body| body.entity)
+    }
+
+    pub fn find_by_entity(&self, entity: Entity) -> Option<&RegisteredBody> {
+        self.bodies.iter().find(|body| body.entity == entity)
+    }
+
+    /// The body that `id` orbits, if it has one
+    pub fn parent_of(&self, id: BodyId) -> Option<&RegisteredBody> {
+        self.get(id)
+            .and_then(|body| body.parent)
+            .and_then(|parent| self.find_by_entity(parent))
+    }
+
+    pub fn find_by_name(&self, name: &str) -> Option<&RegisteredBody> {
+        self.bodies.iter().find(|body| body.name == name)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &RegisteredBody> {
+        self.bodies.iter()
+    }
+
+    /// The body after `current` whose category is one of `categories`,
+    /// wrapping around to the first. With no current body this is the first match.
+    pub fn next_in_categories(&self, current: Option<BodyId>, categories: &[BodyCategory]) -> Option<BodyId> {
+        let candidates: Vec<BodyId> = self.bodies
+            .iter()
+            .filter(|body| categories.contains(&body.category))
+            .map(|body| body.id)
+            .collect();
+        let next_index: usize = current
+            .and_then(|id| candidates.iter().position(|candidate| *candidate == id))
+            .map_or(0, |index| index + 1);
+        candidates.get(next_index).or_else(|| candidates.first()).copied()
+    }
+}
+
+pub struct BodyRegistryPlugin;
+
+impl Plugin for BodyRegistryPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<BodyId>()
+            .register_type::<BodyCategory>()
+            .init_resource::<BodyRegistry>()
+            .add_system_to_stage(CoreStage::PreUpdate, register_bodies)
+            .add_system_to_stage(CoreStage::PostUpdate, unregister_despawned_bodies);
+    }
+}
+
+type NewBody = (Entity, &'static SolarSystemObjectData, Option<&'static BodyCategory>, Option<&'static OrbitParent>);
+
+/// Give every new solar system object an id, whenever it was spawned.
+/// Objects spawned without a category are treated as planets.
+fn register_bodies(
+    mut commands: Commands,
+    mut registry: ResMut<BodyRegistry>,
+    object_query: Query<NewBody, Without<BodyId>>
+) {
+    for (entity, object_data, category, orbit_parent) in &object_query {
+        let category: BodyCategory = category.copied().unwrap_or_default();
+        let parent: Option<Entity> = orbit_parent.map(|orbit_parent| orbit_parent.entity);
+        let id: BodyId = registry.register(entity, object_data.name.clone(), category, parent);
+        commands.entity(entity).insert((id, category));
+    }
+}
+
+fn unregister_despawned_bodies(
+    mut registry: ResMut<BodyRegistry>,
+    body_query: Query<&BodyId>
+) {
+    let despawned: Vec<BodyId> = registry
+        .iter()
+        .filter(|body| body_query.get(body.entity).is_err())
+        .map(|body| body.id)
+        .collect();
+    for id in despawned {
+        registry.unregister(id);
+    }
+}
@@ -0,0 +1,225 @@
+use std::collections::HashMap;
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+
+use crate::{ease_in_out, PhysicsStage, ScaleModeButton, SolarSystemObjectData, RADIUS_TO_TRANSLATION_RATIO};
+
+pub const EARTH_RADIUS_M: f64 = 6.371e6;
+pub const EXAGGERATED_EARTH_RADIUS: f64 = 0.3; // render units, about 30 times true size
+pub const RADIUS_EXAGGERATION_EXPONENT: f64 = 0.5; // evens sizes out, so the Sun does not swallow Mercury
+pub const LOG_DISTANCE_REFERENCE_M: f64 = 1.0e9; // separations well under this stay close to linear
+pub const LOG_DISTANCE_UNITS: f64 = 40.0; // render units per e-fold of distance past the reference
+pub const MIN_BODY_RADIUS: f32 = 1.0e-5; // for bodies without a radius in the catalog
+pub const SCALE_TRANSITION_TIME: f32 = 1.5;
+pub const MAX_PARENT_DEPTH: usize = 16; // guards against a loop of parents
+
+#[derive(Reflect, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ScaleMode {
+    // distances and radii both at RADIUS_TO_TRANSLATION_RATIO
+    True,
+    // true distances, with radii grown on a power law
+    #[default]
+    ExaggeratedRadii,
+    // distances from each body's parent compressed on a log scale, radii as above
+    LogDistance
+}
+
+impl ScaleMode {
+    pub const ALL: [ScaleMode; 3] = [ScaleMode::True, ScaleMode::ExaggeratedRadii, ScaleMode::LogDistance];
+
+    /// Render units drawn for a separation of `metres`
+    pub fn distance(self, metres: f64) -> f64 {
+        match self {
+            ScaleMode::True | ScaleMode::ExaggeratedRadii => metres / RADIUS_TO_TRANSLATION_RATIO,
+            ScaleMode::LogDistance => LOG_DISTANCE_UNITS * (metres / LOG_DISTANCE_REFERENCE_M).ln_1p()
+        }
+    }
+
+    /// Metres of separation drawn as `render_units`, the inverse of `distance`
+    pub fn metres(self, render_units: f64) -> f64 {
+        match self {
+            ScaleMode::True | ScaleMode::ExaggeratedRadii => render_units * RADIUS_TO_TRANSLATION_RATIO,
+            ScaleMode::LogDistance => LOG_DISTANCE_REFERENCE_M * (render_units / LOG_DISTANCE_UNITS).exp_m1()
+        }
+    }
+
+    /// Render units a body of `radius_m` is drawn with
+    pub fn radius(self, radius_m: f64) -> f64 {
+        match self {
+            ScaleMode::True => radius_m / RADIUS_TO_TRANSLATION_RATIO,
+            ScaleMode::ExaggeratedRadii | ScaleMode::LogDistance =>
+                EXAGGERATED_EARTH_RADIUS * (radius_m / EARTH_RADIUS_M).powf(RADIUS_EXAGGERATION_EXPONENT)
+        }
+    }
+
+    fn weights(self) -> [f64; 3] {
+        ScaleMode::ALL.map(|mode| if mode == self { 1.0 } else { 0.0 })
+    }
+}
+
+// HOW DISTANCES AND SIZES ARE DRAWN, K CYCLES THROUGH THE MODES
+// a new mode is eased in from however far the last switch had got, so
+// switching again part way through does not jump
+#[derive(Reflect, Resource)]
+pub struct DisplayScale {
+    pub mode: ScaleMode,
+    // how much of each mode was drawn when the switch started
+    pub from_weights: [f64; 3],
+    pub transition: Timer
+}
+
+impl Default for DisplayScale {
+    fn default() -> DisplayScale {
+        let mode: ScaleMode = ScaleMode::default();
+        let mut transition: Timer = Timer::from_seconds(SCALE_TRANSITION_TIME, TimerMode::Once);
+        transition.tick(transition.duration());
+        DisplayScale { mode, from_weights: mode.weights(), transition }
+    }
+}
+
+impl DisplayScale {
+    pub fn set_mode(&mut self, mode: ScaleMode) {
+        if mode == self.mode {
+            return;
+        }
+        self.from_weights = self.weights();
+        self.mode = mode;
+        self.transition.reset();
+    }
+
+    /// How much of each mode in `ScaleMode::ALL` is drawn right now
+    pub fn weights(&self) -> [f64; 3] {
+        let t: f64 = ease_in_out(self.transition.percent()) as f64;
+        let to_weights: [f64; 3] = self.mode.weights();
+        [0, 1, 2].map(|index| self.from_weights[index] * (1.0 - t) + to_weights[index] * t)
+    }
+
+    /// Whether render units are a fixed number of metres, as the map's grid needs
+    pub fn is_linear(&self) -> bool {
+        self.weights()[ScaleMode::LogDistance as usize] == 0.0
+    }
+
+    /// Render offset drawn for `offset_m` metres from a body's parent, or from a trail's reference
+    pub fn offset(&self, offset_m: DVec3) -> DVec3 {
+        let distance: f64 = offset_m.length();
+        if distance == 0.0 {
+            return DVec3::ZERO;
+        }
+        let drawn: f64 = ScaleMode::ALL.iter()
+            .zip(self.weights())
+            .map(|(mode, weight)| weight * mode.distance(distance))
+            .sum();
+        offset_m * (drawn / distance)
+    }
+
+    /// Roughly how many metres a drawn distance stands for. Mid-switch the
+    /// modes' readings are blended like their drawn distances are.
+    pub fn metres(&self, render_units: f32) -> f64 {
+        ScaleMode::ALL.iter()
+            .zip(self.weights())
+            .map(|(mode, weight)| weight * mode.metres(render_units as f64))
+            .sum()
+    }
+
+    pub fn radius(&self, radius_m: f64) -> f32 {
+        let drawn: f64 = ScaleMode::ALL.iter()
+            .zip(self.weights())
+            .map(|(mode, weight)| weight * mode.radius(radius_m))
+            .sum();
+        (drawn as f32).max(MIN_BODY_RADIUS)
+    }
+
+    /// Where each body is drawn, in render units from the barycentre, from its
+    /// position in metres and its parent. Bodies are placed from their
+    /// parent's drawn position by their scaled separation from it, so moons
+    /// stay around their planets whatever the mode. Bodies without a parent,
+    /// such as the Sun, are placed linearly from the barycentre, as a log
+    /// scale there would swing the whole system around the origin.
+    pub fn layout(&self, bodies: &HashMap<Entity, (DVec3, Option<Entity>)>) -> HashMap<Entity, DVec3> {
+        let mut placed: HashMap<Entity, DVec3> = HashMap::new();
+        for entity in bodies.keys() {
+            self.place(*entity, bodies, &mut placed, 0);
+        }
+        placed
+    }
+
+    fn place(
+        &self,
+        entity: Entity,
+        bodies: &HashMap<Entity, (DVec3, Option<Entity>)>,
+        placed: &mut HashMap<Entity, DVec3>,
+        depth: usize
+    ) -> DVec3 {
+        if let Some(translation) = placed.get(&entity) {
+            return *translation;
+        }
+        let (position, parent) = bodies[&entity];
+        let translation: DVec3 = match parent
+            .filter(|parent| bodies.contains_key(parent) && depth < MAX_PARENT_DEPTH) {
+            Some(parent) => self.place(parent, bodies, placed, depth + 1) + self.offset(position - bodies[&parent].0),
+            None => position / RADIUS_TO_TRANSLATION_RATIO
+        };
+        placed.insert(entity, translation);
+        translation
+    }
+}
+
+pub struct DisplayScalePlugin;
+
+impl Plugin for DisplayScalePlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<ScaleMode>()
+            .register_type::<DisplayScale>()
+            .init_resource::<DisplayScale>()
+            .add_system_to_stage(CoreStage::PreUpdate, advance_scale_transition)
+            .add_system_to_stage(PhysicsStage::Interpolate, size_bodies)
+            .add_system(scale_mode_controls)
+            .add_system(scale_mode_buttons);
+    }
+}
+
+fn scale_mode_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut display_scale: ResMut<DisplayScale>
+) {
+    if keyboard.just_pressed(KeyCode::K) {
+        let next: usize = (display_scale.mode as usize + 1) % ScaleMode::ALL.len();
+        display_scale.set_mode(ScaleMode::ALL[next]);
+    }
+}
+
+fn scale_mode_buttons(
+    mut display_scale: ResMut<DisplayScale>,
+    button_query: Query<(&Interaction, &ScaleModeButton), Changed<Interaction>>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked {
+            display_scale.set_mode(button.mode);
+        }
+    }
+}
+
+// only ticked while running, so the scale reads as changed just while it moves
+fn advance_scale_transition(
+    mut display_scale: ResMut<DisplayScale>,
+    time: Res<Time>
+) {
+    if !display_scale.transition.finished() {
+        display_scale.transition.tick(time.delta());
+    }
+}
+
+/// Scale each body's model so its sphere is drawn at the radius the display
+/// scale gives it
+fn size_bodies(
+    mut object_query: Query<(&mut Transform, &SolarSystemObjectData)>,
+    display_scale: Res<DisplayScale>
+) {
+    for (mut transform, object_data) in &mut object_query {
+        let scale: f32 = display_scale.radius(object_data.radius_m) / object_data.model_radius;
+        if transform.scale.x != scale {
+            transform.scale = Vec3::splat(scale);
+        }
+    }
+}
@@ -0,0 +1,142 @@
+use bevy::prelude::*;
+
+use crate::nbody::SECONDS_PER_DAY;
+use crate::{TimeSpeedSetting, TimeSpeedSettingButton};
+
+pub const DEFAULT_TIME_WARP: f64 = 100000.0; // simulated seconds per real second
+pub const MIN_TIME_WARP: f64 = 1.0;
+pub const MAX_TIME_WARP: f64 = 10.0 * SECONDS_PER_YEAR;
+pub const TIME_WARP_CHANGE_PER_SECOND: f64 = 10.0; // while a warp key is held
+pub const SECONDS_PER_YEAR: f64 = 365.25 * SECONDS_PER_DAY;
+
+// RESOURCE CONTROLLING HOW FAST SIMULATED TIME PASSES
+// factor is simulated seconds per real second, negative when running backwards
+#[derive(Reflect, Resource)]
+pub struct TimeWarp {
+    pub factor: f64,
+    pub paused: bool,
+    pub pending_steps: u32
+}
+
+impl Default for TimeWarp {
+    fn default() -> TimeWarp {
+        TimeWarp {
+            factor: DEFAULT_TIME_WARP,
+            paused: false,
+            pending_steps: 0
+        }
+    }
+}
+
+impl TimeWarp {
+    pub fn is_reversed(&self) -> bool {
+        self.factor < 0.0
+    }
+
+    /// Set the warp speed, keeping the current direction
+    pub fn set_speed(&mut self, simulated_seconds_per_second: f64) {
+        let speed: f64 = simulated_seconds_per_second.abs().clamp(MIN_TIME_WARP, MAX_TIME_WARP);
+        self.factor = if self.is_reversed() { -speed } else { speed };
+    }
+
+    pub fn reverse(&mut self) {
+        self.factor = -self.factor;
+    }
+
+    /// Pause and queue exactly one physics substep in the current direction
+    pub fn single_step(&mut self) {
+        self.paused = true;
+        self.pending_steps += 1;
+    }
+
+    /// Simulated seconds to add this frame, or zero while paused
+    pub fn simulated_seconds(&self, real_seconds: f64) -> f64 {
+        if self.paused { 0.0 } else { self.factor * real_seconds }
+    }
+}
+
+impl TimeSpeedSetting {
+    pub fn warp_speed(&self) -> f64 {
+        match self {
+            TimeSpeedSetting::Slow => 3600.0,
+            TimeSpeedSetting::Normal => DEFAULT_TIME_WARP,
+            TimeSpeedSetting::Fast => 30.0 * SECONDS_PER_DAY
+        }
+    }
+}
+
+/// Warp speed in the largest unit that keeps it readable, e.g. "-2.5 days/s"
+pub fn describe_time_warp(time_warp: &TimeWarp) -> String {
+    let sign: &str = if time_warp.is_reversed() { "-" } else { "" };
+    let paused: &str = if time_warp.paused { " (paused)" } else { "" };
+    format!("{}{}{}", sign, describe_warp_speed(time_warp.factor.abs()), paused)
+}
+
+/// A speed in simulated seconds per real second, e.g. "2.500 days/s"
+pub fn describe_warp_speed(speed: f64) -> String {
+    let (value, unit) = if speed >= SECONDS_PER_YEAR {
+        (speed / SECONDS_PER_YEAR, "years/s")
+    } else if speed >= SECONDS_PER_DAY {
+        (speed / SECONDS_PER_DAY, "days/s")
+    } else if speed >= 3600.0 {
+        (speed / 3600.0, "h/s")
+    } else if speed >= 60.0 {
+        (speed / 60.0, "min/s")
+    } else {
+        (speed, "s/s")
+    };
+    format!("{:.3} {}", value, unit)
+}
+
+pub struct TimeControlPlugin;
+
+impl Plugin for TimeControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<TimeWarp>()
+            .init_resource::<TimeWarp>()
+            .add_system(time_warp_keyboard_controls)
+            .add_system(time_speed_buttons);
+    }
+}
+
+// Space pauses, Period steps once, R reverses, +/- change the warp and Backspace resets it
+fn time_warp_keyboard_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut time_warp: ResMut<TimeWarp>,
+    time: Res<Time>
+) {
+    if keyboard.just_pressed(KeyCode::Space) {
+        time_warp.paused = !time_warp.paused;
+    }
+    if keyboard.just_pressed(KeyCode::Period) {
+        time_warp.single_step();
+    }
+    if keyboard.just_pressed(KeyCode::R) {
+        time_warp.reverse();
+    }
+    if keyboard.just_pressed(KeyCode::Back) {
+        time_warp.set_speed(DEFAULT_TIME_WARP);
+    }
+
+    let change: f64 = TIME_WARP_CHANGE_PER_SECOND.powf(time.delta_seconds_f64());
+    if keyboard.any_pressed([KeyCode::Equals, KeyCode::NumpadAdd]) {
+        let speed: f64 = time_warp.factor.abs() * change;
+        time_warp.set_speed(speed);
+    }
+    if keyboard.any_pressed([KeyCode::Minus, KeyCode::NumpadSubtract]) {
+        let speed: f64 = time_warp.factor.abs() / change;
+        time_warp.set_speed(speed);
+    }
+}
+
+fn time_speed_buttons(
+    mut time_warp: ResMut<TimeWarp>,
+    button_query: Query<(&Interaction, &TimeSpeedSettingButton), Changed<Interaction>>
+) {
+    for (interaction, button) in &button_query {
+        if *interaction == Interaction::Clicked {
+            time_warp.set_speed(button.button_type.warp_speed());
+            time_warp.paused = false;
+        }
+    }
+}
@@ -0,0 +1,363 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use bevy::prelude::*;
+use serde::Deserialize;
+
+use crate::{
+    asset_path, text_style, AutomaticCameraParameters, BodyId, BodyRegistry, CameraSetting, CameraSystem,
+    CameraViewTimer, CurrentPlanetForCamera, DisplayScale, PlanetCameraChangeTimer, SolarSystemObjectData, TimeWarp,
+    UiFont, CAMERA_FLIGHT_TIME, MAX_TIME_WARP, MIN_TIME_WARP, UI_PANEL_COLOR
+};
+
+pub const TOUR_FILE: &str = "tour.ron";
+pub const TOUR_CAPTION_SIZE: f32 = 20.0;
+
+// THE WHOLE TOUR FILE
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawTour {
+    #[serde(default = "default_loop")]
+    loop_tour: bool,
+    stops: Vec<TourStop>
+}
+
+fn default_loop() -> bool {
+    true
+}
+
+/// One stop of the guided tour. The camera flies to `body` and then orbits it
+/// for `dwell_seconds` of real time, `orbit_distance` out and `viewing_height`
+/// up in radii of the body as drawn. `time_warp` is simulated seconds per
+/// second, negative to run backwards, and leaves the warp alone when missing.
+#[derive(Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct TourStop {
+    pub body: String,
+    pub dwell_seconds: f32,
+    pub orbit_distance: f32,
+    pub viewing_height: f32,
+    pub orbit_speed: f32,
+    #[serde(default)]
+    pub time_warp: Option<f64>,
+    #[serde(default)]
+    pub caption: Option<String>
+}
+
+impl TourStop {
+    fn validate(&self) -> Result<(), &'static str> {
+        if self.body.trim().is_empty() {
+            return Err("body must not be empty");
+        }
+        if !(self.dwell_seconds.is_finite() && self.dwell_seconds >= 0.0) {
+            return Err("dwell_seconds must not be negative");
+        }
+        // with no distance the camera would look straight down, with no way to tell which way is up
+        if !(self.orbit_distance.is_finite() && self.orbit_distance > 0.0) {
+            return Err("orbit_distance must be a positive number");
+        }
+        if !(self.viewing_height.is_finite() && self.orbit_speed.is_finite()) {
+            return Err("viewing_height and orbit_speed must be finite numbers");
+        }
+        if let Some(time_warp) = self.time_warp {
+            if !(MIN_TIME_WARP..=MAX_TIME_WARP).contains(&time_warp.abs()) {
+                return Err("time_warp is outside the supported range");
+            }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum TourError {
+    Io { path: PathBuf, error: std::io::Error },
+    Syntax(ron::error::SpannedError),
+    InvalidStop { stop: usize, body: String, message: String }
+}
+
+impl fmt::Display for TourError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TourError::Io { path, error } =>
+                write!(f, "could not read tour {}: {}", path.display(), error),
+            TourError::Syntax(error) =>
+                write!(f, "tour is not valid RON: {}", error),
+            TourError::InvalidStop { stop, body, message } =>
+                write!(f, "tour stop {} ('{}'): {}", stop, body, message),
+        }
+    }
+}
+
+impl std::error::Error for TourError {}
+
+// THE GUIDED TOUR PLAYED BY THE AUTOMATIC CAMERA
+// without stops the automatic camera cycles through the planets instead;
+// G pauses and resumes the tour, N skips to the next stop
+#[derive(Resource, Default)]
+pub struct GuidedTour {
+    pub stops: Vec<TourStop>,
+    pub looping: bool,
+    pub current: usize,
+    pub playing: bool,
+    pub paused: bool,
+    // the body the current stop resolved to, None until the stop has started
+    pub stop_body: Option<BodyId>,
+    pub stop_timer: Timer
+}
+
+impl GuidedTour {
+    pub fn new(stops: Vec<TourStop>, looping: bool) -> GuidedTour {
+        GuidedTour {
+            playing: !stops.is_empty(),
+            stops,
+            looping,
+            ..default()
+        }
+    }
+
+    /// Whether a started stop is framing `body`, so the automatic camera
+    /// should keep the stop's distance rather than its own
+    pub fn is_framing(&self, body: Option<BodyId>) -> bool {
+        self.playing && self.stop_body.is_some() && self.stop_body == body
+    }
+
+    pub fn current_stop(&self) -> Option<&TourStop> {
+        self.stops.get(self.current)
+    }
+
+    /// Move on to the next stop, or stop playing after the last one unless looping
+    pub fn skip(&mut self) {
+        self.stop_body = None;
+        self.current += 1;
+        if self.current >= self.stops.len() {
+            self.current = 0;
+            self.playing = self.looping && !self.stops.is_empty();
+        }
+    }
+
+    /// Take the current stop out of the tour, for good, and move on to the
+    /// next one, so a looping tour cannot keep coming back to it
+    pub fn drop_current_stop(&mut self) {
+        self.stop_body = None;
+        if self.current < self.stops.len() {
+            self.stops.remove(self.current);
+        }
+        if self.current >= self.stops.len() {
+            self.current = 0;
+            self.playing = self.looping && !self.stops.is_empty();
+        }
+    }
+
+    pub fn restart(&mut self) {
+        self.current = 0;
+        self.stop_body = None;
+        self.paused = false;
+        self.playing = !self.stops.is_empty();
+    }
+}
+
+// the caption box along the bottom of the window
+#[derive(Component)]
+pub struct TourCaption;
+
+#[derive(Component)]
+pub struct TourCaptionText;
+
+pub struct GuidedTourPlugin;
+
+impl Plugin for GuidedTourPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GuidedTour>()
+            .add_startup_system(load_guided_tour)
+            .add_startup_system(create_tour_caption)
+            .add_system(tour_controls.before(play_guided_tour))
+            .add_system(play_guided_tour.before(CameraSystem::Automatic))
+            .add_system(show_tour_caption.after(play_guided_tour));
+    }
+}
+
+pub fn load_tour(path: impl AsRef<Path>) -> Result<GuidedTour, TourError> {
+    let path: &Path = path.as_ref();
+    let text: String = std::fs::read_to_string(path)
+        .map_err(|error| TourError::Io { path: path.to_path_buf(), error })?;
+    parse_tour(&text)
+}
+
+pub fn parse_tour(text: &str) -> Result<GuidedTour, TourError> {
+    let raw_tour: RawTour = ron::from_str(text).map_err(TourError::Syntax)?;
+    for (index, stop) in raw_tour.stops.iter().enumerate() {
+        stop.validate().map_err(|message| TourError::InvalidStop {
+            stop: index + 1,
+            body: stop.body.clone(),
+            message: message.to_string()
+        })?;
+    }
+    Ok(GuidedTour::new(raw_tour.stops, raw_tour.loop_tour))
+}
+
+// a missing tour file just leaves the automatic camera cycling through the planets
+fn load_guided_tour(mut commands: Commands) {
+    let path: PathBuf = asset_path(TOUR_FILE);
+    if !path.exists() {
+        return;
+    }
+    match load_tour(&path) {
+        Ok(tour) => commands.insert_resource(tour),
+        Err(error) => eprintln!("Failed to load guided tour: {}", error)
+    }
+}
+
+fn create_tour_caption(mut commands: Commands, font: Res<UiFont>) {
+    commands.spawn((
+        NodeBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: UiRect { left: Val::Px(0.0), bottom: Val::Px(40.0), ..default() },
+                size: Size::new(Val::Percent(100.0), Val::Auto),
+                justify_content: JustifyContent::Center,
+                display: Display::None,
+                ..default()
+            },
+            ..default()
+        },
+        TourCaption
+    )).insert(Name::new("Tour Caption")).with_children(|caption| {
+        caption.spawn(NodeBundle {
+            style: Style {
+                padding: UiRect::all(Val::Px(8.0)),
+                ..default()
+            },
+            background_color: UI_PANEL_COLOR.into(),
+            ..default()
+        }).with_children(|panel| {
+            panel.spawn((
+                TextBundle::from_section("", TextStyle { font_size: TOUR_CAPTION_SIZE, ..text_style(&font) }),
+                TourCaptionText
+            ));
+        });
+    });
+}
+
+fn tour_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut tour: ResMut<GuidedTour>,
+    mut camera_setting: ResMut<CameraSetting>
+) {
+    if keyboard.just_pressed(KeyCode::G) {
+        if tour.playing {
+            tour.paused = !tour.paused;
+        } else {
+            tour.restart();
+            *camera_setting = CameraSetting::Automatic;
+        }
+    }
+    if keyboard.just_pressed(KeyCode::N) && tour.playing {
+        tour.skip();
+    }
+}
+
+/// Start each stop in turn, pointing the automatic camera at its body and
+/// setting the time warp, and hold it for the flight there plus the stop's
+/// dwell time. The tour waits while the camera is manual, and ends if a body
+/// is picked by hand. Stops naming a body that is not in the simulation are
+/// dropped from the tour, so each is only reported once.
+#[allow(clippy::too_many_arguments)]
+fn play_guided_tour(
+    mut tour: ResMut<GuidedTour>,
+    mut current_planet: ResMut<CurrentPlanetForCamera>,
+    mut camera_query: Query<&mut AutomaticCameraParameters>,
+    mut time_warp: ResMut<TimeWarp>,
+    mut camera_timer: ResMut<CameraViewTimer>,
+    mut planet_timer: ResMut<PlanetCameraChangeTimer>,
+    body_query: Query<&SolarSystemObjectData>,
+    camera_setting: Res<CameraSetting>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>,
+    time: Res<Time>
+) {
+    if !tour.playing || *camera_setting != CameraSetting::Automatic {
+        return;
+    }
+    if tour.stop_body.is_some() && current_planet.body != tour.stop_body {
+        // something else took the camera, so hand it back to the planet cycle
+        tour.playing = false;
+        tour.stop_body = None;
+        return;
+    }
+    // the tour replaces the planet cycle and the switch to manual
+    camera_timer.timer.pause();
+    planet_timer.timer.pause();
+
+    if tour.stop_body.is_none() {
+        let Some(stop) = tour.current_stop().cloned() else { return };
+        let Some(body) = registry.find_by_name(&stop.body) else {
+            // bodies are registered a frame after startup
+            if registry.iter().next().is_none() {
+                return;
+            }
+            eprintln!("Tour stop '{}' is not a body in the simulation, dropping it", stop.body);
+            tour.drop_current_stop();
+            if !tour.playing {
+                planet_timer.timer.reset();
+                planet_timer.timer.unpause();
+            }
+            return;
+        };
+        current_planet.body = Some(body.id);
+        if let Some(factor) = stop.time_warp {
+            time_warp.set_speed(factor);
+            if time_warp.is_reversed() != (factor < 0.0) {
+                time_warp.reverse();
+            }
+        }
+        tour.stop_body = Some(body.id);
+        tour.stop_timer = Timer::from_seconds(CAMERA_FLIGHT_TIME + stop.dwell_seconds, TimerMode::Once);
+    }
+
+    // set every frame, so the framing keeps up with changes to the display scale
+    let radius: Option<f32> = tour.stop_body
+        .and_then(|id| registry.entity(id))
+        .and_then(|entity| body_query.get(entity).ok())
+        .map(|object_data| display_scale.radius(object_data.radius_m));
+    if let (Some(stop), Some(radius)) = (tour.current_stop().cloned(), radius) {
+        for mut parameters in &mut camera_query {
+            parameters.orbit_distance = stop.orbit_distance * radius;
+            parameters.viewing_height = stop.viewing_height * radius;
+            parameters.orbit_speed = stop.orbit_speed;
+        }
+    }
+
+    if !tour.paused {
+        tour.stop_timer.tick(time.delta());
+        if tour.stop_timer.finished() {
+            tour.skip();
+            if !tour.playing {
+                planet_timer.timer.reset();
+                planet_timer.timer.unpause();
+            }
+        }
+    }
+}
+
+fn show_tour_caption(
+    mut caption_query: Query<&mut Style, With<TourCaption>>,
+    mut text_query: Query<&mut Text, With<TourCaptionText>>,
+    tour: Res<GuidedTour>,
+    camera_setting: Res<CameraSetting>
+) {
+    let caption: Option<&String> = tour.current_stop()
+        .filter(|_| tour.playing && tour.stop_body.is_some() && *camera_setting == CameraSetting::Automatic)
+        .and_then(|stop| stop.caption.as_ref());
+    for mut style in &mut caption_query {
+        let display: Display = if caption.is_some() { Display::Flex } else { Display::None };
+        if style.display != display {
+            style.display = display;
+        }
+    }
+    let Some(caption) = caption else { return };
+    for mut text in &mut text_query {
+        if text.sections[0].value != *caption {
+            text.sections[0].value.clone_from(caption);
+        }
+    }
+}
@@ -0,0 +1,262 @@
+use std::collections::VecDeque;
+
+use bevy::math::DVec3;
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use bevy::render::view::NoFrustumCulling;
+
+use crate::nbody::SECONDS_PER_DAY;
+use crate::{
+    BodyId, BodyRegistry, CurrentPlanetForCamera, DisplayScale, NBodySimulation, ParentRelativeOrbit, PhysicsBody,
+    PhysicsClock, PhysicsStage, PhysicsSystem, RenderOrigin, SimulationClock, SolarSystemObjectData
+};
+
+pub const TRAIL_SAMPLES: usize = 256;
+pub const DEFAULT_TRAIL_DAYS: f64 = 365.0; // for bodies without a closed orbit
+pub const TRAIL_MAX_ALPHA: f32 = 0.8;
+pub const TRAIL_COLORS: [Color; 6] = [
+    Color::rgb(0.4, 0.7, 1.0),
+    Color::rgb(1.0, 0.7, 0.3),
+    Color::rgb(0.5, 1.0, 0.5),
+    Color::rgb(1.0, 0.5, 0.6),
+    Color::rgb(0.8, 0.6, 1.0),
+    Color::rgb(1.0, 1.0, 0.5)
+];
+
+// HOW MUCH HISTORY A TRAIL SHOWS
+// in simulated days, or in orbits of the body around its parent
+#[derive(Reflect, Clone, Copy, Debug, PartialEq)]
+pub enum TrailLength {
+    Days(f64),
+    Orbits(f64)
+}
+
+// SETTINGS FOR THE ORBIT TRAILS, T TOGGLES THEM AND O DRAWS THEM AROUND THE FOCUSED BODY
+// changing the reference body clears the trails, as old samples are relative to the old one
+#[derive(Reflect, Resource)]
+pub struct OrbitTrailSettings {
+    pub enabled: bool,
+    pub length: TrailLength,
+    pub reference: Option<BodyId>
+}
+
+impl Default for OrbitTrailSettings {
+    fn default() -> OrbitTrailSettings {
+        OrbitTrailSettings {
+            enabled: true,
+            length: TrailLength::Orbits(1.0),
+            reference: None
+        }
+    }
+}
+
+struct TrailSample {
+    time: f64,
+    position: DVec3 // metres, relative to the reference body when there is one
+}
+
+// the line drawn behind one body, samples are kept in time order
+#[derive(Component)]
+pub struct OrbitTrail {
+    pub body: Entity,
+    pub length_seconds: f64,
+    samples: VecDeque<TrailSample>
+}
+
+impl OrbitTrail {
+    // the samples the body has just passed through, oldest first
+    fn behind(&self, now: f64, reversed: bool) -> Vec<&TrailSample> {
+        if reversed {
+            self.samples.iter().rev()
+                .filter(|sample| sample.time >= now && sample.time - now <= self.length_seconds)
+                .collect()
+        } else {
+            self.samples.iter()
+                .filter(|sample| sample.time <= now && now - sample.time <= self.length_seconds)
+                .collect()
+        }
+    }
+}
+
+// marks a body that already has a trail
+#[derive(Component)]
+pub struct HasOrbitTrail;
+
+pub struct OrbitTrailPlugin;
+
+impl Plugin for OrbitTrailPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<OrbitTrailSettings>()
+            .init_resource::<OrbitTrailSettings>()
+            .add_system(spawn_orbit_trails)
+            .add_system(orbit_trail_controls)
+            .add_system_to_stage(PhysicsStage::Step, sample_orbit_trails.after(PhysicsSystem::Move))
+            .add_system(draw_orbit_trails);
+    }
+}
+
+/// Line colour for a body's trail, shared with its predicted orbit
+pub fn trail_color(body: Entity) -> Color {
+    TRAIL_COLORS[body.index() as usize % TRAIL_COLORS.len()]
+}
+
+impl TrailLength {
+    /// Simulated seconds of history for a body that takes `period` seconds per orbit
+    pub fn seconds(&self, period: Option<f64>) -> f64 {
+        match *self {
+            TrailLength::Days(days) => days * SECONDS_PER_DAY,
+            TrailLength::Orbits(orbits) => period.map_or(DEFAULT_TRAIL_DAYS * SECONDS_PER_DAY, |period| orbits * period)
+        }
+    }
+}
+
+fn spawn_orbit_trails(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    body_query: Query<(Entity, &SolarSystemObjectData), Without<HasOrbitTrail>>,
+    trail_query: Query<(Entity, &OrbitTrail)>,
+    trailed_query: Query<(), With<HasOrbitTrail>>
+) {
+    for (entity, object_data) in &body_query {
+        commands.spawn((
+            PbrBundle {
+                mesh: meshes.add(Mesh::new(PrimitiveTopology::LineStrip)),
+                material: materials.add(StandardMaterial {
+                    base_color: trail_color(entity),
+                    unlit: true,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                }),
+                // hidden until there are samples to draw
+                visibility: Visibility { is_visible: false },
+                ..default()
+            },
+            // the mesh changes every frame, so its bounds from spawning mean nothing
+            NoFrustumCulling,
+            OrbitTrail { body: entity, length_seconds: 0.0, samples: VecDeque::new() }
+        )).insert(Name::new(format!("{} Trail", object_data.name)));
+        commands.entity(entity).insert(HasOrbitTrail);
+    }
+    for (trail_entity, trail) in &trail_query {
+        if trailed_query.get(trail.body).is_err() {
+            commands.entity(trail_entity).despawn_recursive();
+        }
+    }
+}
+
+fn orbit_trail_controls(
+    keyboard: Res<Input<KeyCode>>,
+    mut settings: ResMut<OrbitTrailSettings>,
+    current_planet: Res<CurrentPlanetForCamera>
+) {
+    if keyboard.just_pressed(KeyCode::T) {
+        settings.enabled = !settings.enabled;
+    }
+    if keyboard.just_pressed(KeyCode::O) {
+        settings.reference = if settings.reference.is_some() { None } else { current_planet.body };
+    }
+}
+
+/// Record positions straight from the n-body state after each physics step,
+/// spaced so every trail holds about `TRAIL_SAMPLES` points whatever its length.
+/// Samples up to a trail length either side of now are kept, so after time is
+/// reversed the body runs back over the path it already recorded.
+#[allow(clippy::too_many_arguments)]
+fn sample_orbit_trails(
+    mut trail_query: Query<&mut OrbitTrail>,
+    mut applied_reference: Local<Option<BodyId>>,
+    body_query: Query<(&PhysicsBody, Option<&ParentRelativeOrbit>)>,
+    simulation: Res<NBodySimulation>,
+    clock: Res<SimulationClock>,
+    physics_clock: Res<PhysicsClock>,
+    settings: Res<OrbitTrailSettings>,
+    registry: Res<BodyRegistry>
+) {
+    if *applied_reference != settings.reference {
+        *applied_reference = settings.reference;
+        for mut trail in &mut trail_query {
+            trail.samples.clear();
+        }
+    }
+    let reference_position: DVec3 = settings.reference
+        .and_then(|id| registry.entity(id))
+        .and_then(|entity| body_query.get(entity).ok())
+        .map_or(DVec3::ZERO, |(physics_body, _)| simulation.state.bodies[physics_body.index].position);
+    let now: f64 = clock.tdb_seconds_since_j2000;
+
+    for mut trail in &mut trail_query {
+        let Ok((physics_body, relative_orbit)) = body_query.get(trail.body) else { continue };
+        let length: f64 = settings.length.seconds(relative_orbit.and_then(|orbit| orbit.period_seconds()));
+        trail.length_seconds = length;
+
+        while trail.samples.front().is_some_and(|sample| now - sample.time > length) {
+            trail.samples.pop_front();
+        }
+        while trail.samples.back().is_some_and(|sample| sample.time - now > length) {
+            trail.samples.pop_back();
+        }
+
+        // only extend the end the body is moving away from
+        let interval: f64 = length / TRAIL_SAMPLES as f64;
+        let sample: TrailSample = TrailSample {
+            time: now,
+            position: simulation.state.bodies[physics_body.index].position - reference_position
+        };
+        if physics_clock.reversed {
+            if trail.samples.front().is_none_or(|first| first.time - now >= interval) {
+                trail.samples.push_front(sample);
+            }
+        } else if trail.samples.back().is_none_or(|last| now - last.time >= interval) {
+            trail.samples.push_back(sample);
+        }
+    }
+}
+
+/// Rebuild each trail's line from its samples, ending at the body's rendered
+/// position and fading out towards the oldest sample. Samples are laid out
+/// from the reference body by the display scale, so with log distances only
+/// paths around the reference keep their shape.
+#[allow(clippy::too_many_arguments)]
+fn draw_orbit_trails(
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut trail_query: Query<(&OrbitTrail, &Handle<Mesh>, &mut Visibility)>,
+    body_query: Query<&Transform, With<SolarSystemObjectData>>,
+    clock: Res<SimulationClock>,
+    physics_clock: Res<PhysicsClock>,
+    settings: Res<OrbitTrailSettings>,
+    registry: Res<BodyRegistry>,
+    display_scale: Res<DisplayScale>,
+    render_origin: Res<RenderOrigin>
+) {
+    // summed in f64, as the barycentre can be far from the render origin
+    let reference_entity: Option<Entity> = settings.reference.and_then(|id| registry.entity(id));
+    let reference_translation: DVec3 = reference_entity
+        .and_then(|entity| body_query.get(entity).ok())
+        .map_or(-render_origin.position, |transform| transform.translation.as_dvec3());
+
+    for (trail, mesh_handle, mut visibility) in &mut trail_query {
+        let behind: Vec<&TrailSample> = trail.behind(clock.tdb_seconds_since_j2000, physics_clock.reversed);
+        let visible: bool = settings.enabled && behind.len() > 1 && Some(trail.body) != reference_entity;
+        if visibility.is_visible != visible {
+            visibility.is_visible = visible;
+        }
+        let (true, Some(mesh), Ok(body_transform)) =
+            (visible, meshes.get_mut(mesh_handle), body_query.get(trail.body)) else { continue };
+
+        let mut positions: Vec<[f32; 3]> = behind
+            .iter()
+            .map(|sample| (reference_translation + display_scale.offset(sample.position)).as_vec3().to_array())
+            .collect();
+        positions.push(body_transform.translation.to_array());
+        let count: usize = positions.len();
+        let colors: Vec<[f32; 4]> = (0..count)
+            .map(|index| [1.0, 1.0, 1.0, TRAIL_MAX_ALPHA * index as f32 / (count - 1) as f32])
+            .collect();
+
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, vec![[0.0, 1.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, vec![[0.0, 0.0]; count]);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+    }
+}
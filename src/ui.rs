@@ -1,7 +1,23 @@
-use std::time::Duration;
 use bevy::prelude::*;
 
-#[derive(Reflect, Component, Clone, Copy, Debug, Default)]
+use crate::{
+    BodyCategory, BodyId, BodyRegistry, CurrentPlanetForCamera, DisplayScale, InsetView, PictureInPicture, ScaleMode,
+    TimeWarp
+};
+
+pub const UI_FONT_FILE: &str = "fonts/DejaVuSans.ttf";
+pub const UI_FONT_SIZE: f32 = 16.0;
+pub const UI_PANEL_WIDTH: f32 = 220.0;
+pub const UI_BUTTON_HEIGHT: f32 = 28.0;
+
+pub const UI_PANEL_COLOR: Color = Color::rgba(0.05, 0.05, 0.1, 0.75);
+pub const UI_TEXT_COLOR: Color = Color::rgb(0.9, 0.9, 0.9);
+pub const NORMAL_BUTTON_COLOR: Color = Color::rgb(0.15, 0.15, 0.2);
+pub const HOVERED_BUTTON_COLOR: Color = Color::rgb(0.25, 0.25, 0.35);
+pub const PRESSED_BUTTON_COLOR: Color = Color::rgb(0.35, 0.55, 0.35);
+pub const SELECTED_BUTTON_COLOR: Color = Color::rgb(0.2, 0.35, 0.6);
+
+#[derive(Reflect, Resource, Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
 #[reflect(Component)]
 pub enum CameraSetting {
     Manual,
@@ -12,10 +28,10 @@ pub enum CameraSetting {
 #[derive(Reflect, Component, Default, Debug)]
 #[reflect(Component)]
 pub struct CameraSettingButton {
-    button_type: CameraSetting
+    pub button_type: CameraSetting
 }
 
-#[derive(Reflect, Component, Default, Debug)]
+#[derive(Reflect, Component, Clone, Copy, Default, Debug, PartialEq, Eq)]
 #[reflect(Component)]
 pub enum TimeSpeedSetting {
     Slow,
@@ -27,7 +43,51 @@ pub enum TimeSpeedSetting {
 #[derive(Reflect, Component, Default, Debug)]
 #[reflect(Component)]
 pub struct TimeSpeedSettingButton {
-    button_type: TimeSpeedSetting
+    pub button_type: TimeSpeedSetting
+}
+
+// one button per registered body, focusing the camera on it
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct BodySelectorButton {
+    pub body: BodyId
+}
+
+// the node the body buttons are rebuilt inside
+#[derive(Component)]
+pub struct BodySelectorList;
+
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct ScaleModeButton {
+    pub mode: ScaleMode
+}
+
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct InsetViewButton {
+    pub view: InsetView
+}
+
+// steps the inset's target back or forward through the bodies
+#[derive(Reflect, Component, Default, Debug)]
+#[reflect(Component)]
+pub struct InsetTargetButton {
+    pub step: i32
+}
+
+// names the inset's target between its two buttons
+#[derive(Component)]
+pub struct InsetTargetText;
+
+// FONT SHARED BY EVERY PIECE OF UI TEXT
+#[derive(Resource)]
+pub struct UiFont(pub Handle<Font>);
+
+impl FromWorld for UiFont {
+    fn from_world(world: &mut World) -> UiFont {
+        UiFont(world.resource::<AssetServer>().load(UI_FONT_FILE))
+    }
 }
 
 pub struct UIPlugin;
@@ -36,10 +96,225 @@ impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<TimeSpeedSettingButton>()
             .register_type::<CameraSettingButton>()
-            .add_startup_system(create_ui);
+            .register_type::<BodySelectorButton>()
+            .register_type::<ScaleModeButton>()
+            .register_type::<InsetViewButton>()
+            .register_type::<InsetTargetButton>()
+            .init_resource::<UiFont>()
+            .add_startup_system(create_ui)
+            .add_system(update_body_selector)
+            .add_system(style_camera_setting_buttons)
+            .add_system(style_time_speed_buttons)
+            .add_system(style_body_selector_buttons)
+            .add_system(style_scale_mode_buttons)
+            .add_system(style_inset_view_buttons);
+    }
+}
+
+/// Colour for a button in the given interaction state. Hover and press
+/// feedback win over showing which option is currently selected.
+pub fn button_color(interaction: Interaction, selected: bool) -> Color {
+    match interaction {
+        Interaction::Clicked => PRESSED_BUTTON_COLOR,
+        Interaction::Hovered => HOVERED_BUTTON_COLOR,
+        Interaction::None if selected => SELECTED_BUTTON_COLOR,
+        Interaction::None => NORMAL_BUTTON_COLOR
+    }
+}
+
+pub fn text_style(font: &UiFont) -> TextStyle {
+    TextStyle {
+        font: font.0.clone(),
+        font_size: UI_FONT_SIZE,
+        color: UI_TEXT_COLOR
     }
 }
 
-fn create_ui(mut commands: Commands, asset_server: Res<AssetServer>){
-    
-}
\ No newline at end of file
+fn spawn_heading(parent: &mut ChildBuilder, font: &UiFont, heading: &str) {
+    parent.spawn(TextBundle::from_section(heading, text_style(font)).with_style(Style {
+        margin: UiRect { top: Val::Px(8.0), bottom: Val::Px(4.0), ..default() },
+        ..default()
+    }));
+}
+
+fn spawn_button(parent: &mut ChildBuilder, font: &UiFont, label: &str, width: Val, marker: impl Bundle) {
+    parent.spawn((
+        ButtonBundle {
+            style: Style {
+                size: Size::new(width, Val::Px(UI_BUTTON_HEIGHT)),
+                margin: UiRect::all(Val::Px(2.0)),
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            background_color: NORMAL_BUTTON_COLOR.into(),
+            ..default()
+        },
+        marker
+    )).with_children(|button| {
+        button.spawn(TextBundle::from_section(label, text_style(font)));
+    });
+}
+
+// a row of equal width buttons
+fn spawn_button_row(parent: &mut ChildBuilder, build: impl FnOnce(&mut ChildBuilder)) {
+    parent.spawn(NodeBundle {
+        style: Style {
+            flex_direction: FlexDirection::Row,
+            size: Size::new(Val::Percent(100.0), Val::Auto),
+            ..default()
+        },
+        ..default()
+    }).with_children(build);
+}
+
+// panel down the right hand side of the window
+fn create_ui(mut commands: Commands, font: Res<UiFont>) {
+    commands.spawn(NodeBundle {
+        style: Style {
+            position_type: PositionType::Absolute,
+            position: UiRect { right: Val::Px(10.0), top: Val::Px(10.0), ..default() },
+            size: Size::new(Val::Px(UI_PANEL_WIDTH), Val::Auto),
+            flex_direction: FlexDirection::Column,
+            padding: UiRect::all(Val::Px(8.0)),
+            ..default()
+        },
+        background_color: UI_PANEL_COLOR.into(),
+        ..default()
+    }).insert(Name::new("UI Panel")).with_children(|panel| {
+        spawn_heading(panel, &font, "Camera");
+        spawn_button_row(panel, |row| {
+            for (label, button_type) in [("Manual", CameraSetting::Manual), ("Automatic", CameraSetting::Automatic)] {
+                spawn_button(row, &font, label, Val::Percent(50.0), CameraSettingButton { button_type });
+            }
+        });
+
+        spawn_heading(panel, &font, "Time");
+        spawn_button_row(panel, |row| {
+            for (label, button_type) in [
+                ("Slow", TimeSpeedSetting::Slow),
+                ("Normal", TimeSpeedSetting::Normal),
+                ("Fast", TimeSpeedSetting::Fast)
+            ] {
+                spawn_button(row, &font, label, Val::Percent(33.3), TimeSpeedSettingButton { button_type });
+            }
+        });
+
+        spawn_heading(panel, &font, "Scale");
+        spawn_button_row(panel, |row| {
+            for (label, mode) in [
+                ("True", ScaleMode::True),
+                ("Radii", ScaleMode::ExaggeratedRadii),
+                ("Log", ScaleMode::LogDistance)
+            ] {
+                spawn_button(row, &font, label, Val::Percent(33.3), ScaleModeButton { mode });
+            }
+        });
+
+        spawn_heading(panel, &font, "Picture in picture");
+        spawn_button_row(panel, |row| {
+            for (label, view) in [("Off", InsetView::Off), ("Body", InsetView::CloseUp), ("Map", InsetView::Map)] {
+                spawn_button(row, &font, label, Val::Percent(33.3), InsetViewButton { view });
+            }
+        });
+        spawn_button_row(panel, |row| {
+            spawn_button(row, &font, "<", Val::Percent(20.0), InsetTargetButton { step: -1 });
+            row.spawn((
+                TextBundle::from_section("", text_style(&font))
+                    .with_text_alignment(TextAlignment::CENTER)
+                    .with_style(Style {
+                        size: Size::new(Val::Percent(60.0), Val::Auto),
+                        align_self: AlignSelf::Center,
+                        ..default()
+                    }),
+                InsetTargetText
+            ));
+            spawn_button(row, &font, ">", Val::Percent(20.0), InsetTargetButton { step: 1 });
+        });
+
+        spawn_heading(panel, &font, "Bodies");
+        panel.spawn((
+            NodeBundle {
+                style: Style {
+                    flex_direction: FlexDirection::Column,
+                    size: Size::new(Val::Percent(100.0), Val::Auto),
+                    ..default()
+                },
+                ..default()
+            },
+            BodySelectorList
+        ));
+    });
+}
+
+/// Rebuild the body list whenever bodies are registered or removed
+fn update_body_selector(
+    mut commands: Commands,
+    registry: Res<BodyRegistry>,
+    font: Res<UiFont>,
+    list_query: Query<Entity, With<BodySelectorList>>
+) {
+    if !registry.is_changed() {
+        return;
+    }
+    for list in &list_query {
+        commands.entity(list).despawn_descendants();
+        commands.entity(list).with_children(|list| {
+            for body in registry.iter() {
+                // moons are indented under the planets they follow in the catalog
+                let label: String = match body.category {
+                    BodyCategory::Moon => format!("  {}", body.name),
+                    _ => body.name.clone()
+                };
+                spawn_button(list, &font, &label, Val::Percent(100.0), BodySelectorButton { body: body.id });
+            }
+        });
+    }
+}
+
+fn style_camera_setting_buttons(
+    mut button_query: Query<(&Interaction, &CameraSettingButton, &mut BackgroundColor)>,
+    camera_setting: Res<CameraSetting>
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        *color = button_color(*interaction, button.button_type == *camera_setting).into();
+    }
+}
+
+fn style_time_speed_buttons(
+    mut button_query: Query<(&Interaction, &TimeSpeedSettingButton, &mut BackgroundColor)>,
+    time_warp: Res<TimeWarp>
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        let speed: f64 = button.button_type.warp_speed();
+        let selected: bool = (time_warp.factor.abs() - speed).abs() <= 1e-9 * speed;
+        *color = button_color(*interaction, selected).into();
+    }
+}
+
+fn style_body_selector_buttons(
+    mut button_query: Query<(&Interaction, &BodySelectorButton, &mut BackgroundColor)>,
+    current_planet: Res<CurrentPlanetForCamera>
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        *color = button_color(*interaction, current_planet.body == Some(button.body)).into();
+    }
+}
+
+fn style_scale_mode_buttons(
+    mut button_query: Query<(&Interaction, &ScaleModeButton, &mut BackgroundColor)>,
+    display_scale: Res<DisplayScale>
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        *color = button_color(*interaction, button.mode == display_scale.mode).into();
+    }
+}
+
+fn style_inset_view_buttons(
+    mut button_query: Query<(&Interaction, &InsetViewButton, &mut BackgroundColor)>,
+    picture_in_picture: Res<PictureInPicture>
+) {
+    for (interaction, button, mut color) in &mut button_query {
+        *color = button_color(*interaction, button.view == picture_in_picture.view).into();
+    }
+}